@@ -3,6 +3,15 @@ defmodule Elxvips.ResizeOptions do
     width: :auto,
     height: :auto,
     resize_type: :auto, # :type conflicts with rust keyword "type"
+    gravity: :centre,
+    kernel: :lanczos3,
+    linear: false,
+    without_enlargement: false,
+    max_width: 0,
+    max_height: 0,
+    longest_edge: 0,
+    scale: 0.0,
+    focal_point: nil, # { x, y } relative to the image, 0..1
   ]
 end
 
@@ -17,6 +26,151 @@ defmodule Elxvips.SaveOptions do
   ]
 end
 
+defmodule Elxvips.CropOptions do
+  defstruct [
+    left: 0,
+    top: 0,
+    width: 0,
+    height: 0,
+    stage: :before, # :before or :after resize
+  ]
+end
+
+defmodule Elxvips.RotateOptions do
+  defstruct [
+    angle: 0.0,
+    background: [],
+  ]
+end
+
+defmodule Elxvips.EmbedOptions do
+  defstruct [
+    width: 0,
+    height: 0,
+    gravity: :centre,
+    background: [],
+  ]
+end
+
+defmodule Elxvips.AffineOptions do
+  defstruct [
+    matrix: [ 1.0, 0.0, 0.0, 1.0 ], # [ a, b, c, d ]
+    odx: 0.0,
+    ody: 0.0,
+    idx: 0.0,
+    idy: 0.0,
+    interpolate: :bilinear,
+    background: [],
+  ]
+end
+
+defmodule Elxvips.PerspectiveOptions do
+  defstruct [
+    corners: [], # [ top_left, top_right, bottom_right, bottom_left ] as { x, y }
+    width: 0,
+    height: 0,
+    interpolate: :bilinear,
+    background: [],
+  ]
+end
+
+defmodule Elxvips.BlurOptions do
+  defstruct [
+    sigma: 1.0,
+  ]
+end
+
+defmodule Elxvips.CompositeOptions do
+  defstruct [
+    path: nil,
+    bytes: nil,
+    left: 0,
+    top: 0,
+    gravity: :north_west,
+    blend: :over,
+  ]
+end
+
+defmodule Elxvips.Variant do
+  defstruct [
+    resize: %Elxvips.ResizeOptions{},
+    save: %Elxvips.SaveOptions{},
+  ]
+end
+
+defmodule Elxvips.Smartcrop do
+  # crop rectangle and attention centre chosen by a smart crop,
+  # relative to the resized image of image_width x image_height it was taken from
+  defstruct [
+    left: 0,
+    top: 0,
+    width: 0,
+    height: 0,
+    attention_x: 0,
+    attention_y: 0,
+    image_width: 0,
+    image_height: 0,
+  ]
+end
+
+defmodule Elxvips.TrimOptions do
+  defstruct [
+    threshold: 10.0,
+    background: [], # empty list means the top-left pixel
+  ]
+end
+
+defmodule Elxvips.CacheOptions do
+  defstruct [
+    max: nil, # operations kept in the cache
+    max_mem: nil, # bytes of memory used by cached operations
+    max_files: nil, # files kept open by cached operations
+  ]
+end
+
+defmodule Elxvips.MemoryStats do
+  # memory tracked by libvips, pixel buffers included, and the state of its operation cache, sizes in bytes
+  defstruct [
+    mem: 0,
+    mem_highwater: 0,
+    allocs: 0,
+    files: 0,
+    cache_size: 0,
+    cache_max: 0,
+    cache_max_mem: 0,
+    cache_max_files: 0,
+  ]
+end
+
+defmodule Elxvips.BlockOptions do
+  # operations are named by nickname, "magickload", or class, "VipsForeignLoadMagick", blocking a class blocks its subclasses
+  defstruct [
+    block_untrusted: nil, # blocks or unblocks the operations libvips flags as untrusted, nil leaves them as they are
+    block: [], # operations blocked after the untrusted ones
+    allow: [], # operations unblocked last, reopening part of a blocked class
+  ]
+end
+
+defmodule Elxvips.ProgressOptions do
+  # {:elxvips_progress, tag, percent} messages are sent to pid while the image is encoded
+  defstruct [
+    pid: nil,
+    tag: nil,
+  ]
+end
+
+defmodule Elxvips.LoadLimits do
+  # checked against the image header before any pixels are decoded, nil disables a limit
+  defstruct [
+    max_pixels: nil,
+    max_width: nil,
+    max_height: nil,
+    max_pages: nil,
+    max_bytes: nil, # size of the encoded input
+    fail_on: :none, # :none, :truncated, :error or :warning, the load fails on damaged input of at least this severity
+  ]
+end
+
 defmodule Elxvips.ImageFile do
   defstruct [
     path: :nil,
@@ -29,6 +183,21 @@ defmodule Elxvips.ImageFile do
     pdf: false,
     page: 0,
     n: 1,
+    crop: nil,
+    autorotate: true,
+    rotate: nil,
+    flip_horizontal: false,
+    flip_vertical: false,
+    embed: nil,
+    trim: nil,
+    affine: nil,
+    perspective: nil,
+    operations: [], # applied in order after the options above, see Elxvips.operations/2
+    smartcrop: nil, # %Elxvips.Smartcrop{} of the processing that produced this image
+    handle: nil, # decoded image kept by load/1, reused instead of reading the source again
+    timeout_ms: nil, # processing taking longer returns {:error, :timeout}, see Elxvips.timeout/2
+    progress: nil, # %Elxvips.ProgressOptions{} receiving the percentage done, see Elxvips.progress/2
+    limits: nil, # %Elxvips.LoadLimits{} checked before decoding, see Elxvips.limits/2
   ]
 end
 
@@ -44,6 +213,22 @@ defmodule Elxvips.ImageBytes do
     pdf: false,
     page: 0,
     n: 1,
+    crop: nil,
+    autorotate: true,
+    rotate: nil,
+    flip_horizontal: false,
+    flip_vertical: false,
+    embed: nil,
+    trim: nil,
+    affine: nil,
+    perspective: nil,
+    operations: [], # applied in order after the options above, see Elxvips.operations/2
+    smartcrop: nil, # %Elxvips.Smartcrop{} of the processing that produced this image
+    handle: nil, # decoded image kept by load/1, reused instead of reading the source again
+    timeout_ms: nil, # processing taking longer returns {:error, :timeout}, see Elxvips.timeout/2
+    progress: nil, # %Elxvips.ProgressOptions{} receiving the percentage done, see Elxvips.progress/2
+    limits: nil, # %Elxvips.LoadLimits{} checked before decoding, see Elxvips.limits/2
+    stream: nil, # enumerable of chunks read while decoding, see Elxvips.from_stream/1
   ]
 end
 
@@ -54,15 +239,29 @@ defmodule Elxvips do
   alias Elxvips.ImageFile, as: ImageFile
   alias Elxvips.ImageBytes, as: ImageBytes
   alias Elxvips.SaveOptions, as: SaveOptions
+  alias Elxvips.CropOptions, as: CropOptions
+  alias Elxvips.RotateOptions, as: RotateOptions
+  alias Elxvips.EmbedOptions, as: EmbedOptions
+  alias Elxvips.TrimOptions, as: TrimOptions
+  alias Elxvips.AffineOptions, as: AffineOptions
+  alias Elxvips.PerspectiveOptions, as: PerspectiveOptions
+  alias Elxvips.BlurOptions, as: BlurOptions
+  alias Elxvips.CompositeOptions, as: CompositeOptions
+  alias Elxvips.Variant, as: Variant
+  alias Elxvips.CacheOptions, as: CacheOptions
+  alias Elxvips.ProgressOptions, as: ProgressOptions
+  alias Elxvips.BlockOptions, as: BlockOptions
+  alias Elxvips.LoadLimits, as: LoadLimits
 
   # creating new image from an existing image path
   defp process_to_file( image_file = %ImageFile{}, path ) when is_binary( path ) do
     image_file = %ImageFile{ image_file |
       :save => Kernel.struct( image_file.save, [ path: path ] )
     }
-    with :ok <- Elxvips.Native.vips_process_file_to_file( image_file ) do
+    with { :ok, smartcrop } <- Elxvips.Native.vips_process_file_to_file_smartcrop( image_file ) do
       { :ok, %ImageFile{
         :path => path,
+        :smartcrop => smartcrop,
       } }
     else
       err -> err
@@ -72,9 +271,10 @@ defmodule Elxvips do
     image_bytes = %ImageBytes{ image_bytes |
       :save => Kernel.struct( image_bytes.save, [ path: path ] )
     }
-    with :ok <- Elxvips.Native.vips_process_bytes_to_file( image_bytes ) do
+    with { :ok, smartcrop } <- Elxvips.Native.vips_process_bytes_to_file_smartcrop( image_bytes ) do
       { :ok, %ImageFile{
         :path => path,
+        :smartcrop => smartcrop,
       } }
     else
       err -> err
@@ -82,9 +282,10 @@ defmodule Elxvips do
   end
   # In case the we have a image path as image_bytes
   defp process_to_bytes( image_file = %ImageFile{ :path => path } ) when is_binary( path )  do
-    with { :ok, bytes } <- Elxvips.Native.vips_process_file_to_bytes( image_file ) do
+    with { :ok, bytes, smartcrop } <- Elxvips.Native.vips_process_file_to_bytes_smartcrop( image_file ) do
       { :ok, %ImageBytes{
         :bytes => bytes,
+        :smartcrop => smartcrop,
       } }
     else
       err -> err
@@ -92,9 +293,10 @@ defmodule Elxvips do
   end
   # In case we have raw bytes
   defp process_to_bytes( image_bytes = %ImageBytes{ :bytes => bytes } ) when is_bitstring( bytes ) do
-    with { :ok, bytes } <- Elxvips.Native.vips_process_bytes_to_bytes( image_bytes ) do
+    with { :ok, bytes, smartcrop } <- Elxvips.Native.vips_process_bytes_to_bytes_smartcrop( image_bytes ) do
       { :ok, %ImageBytes{
         :bytes => bytes,
+        :smartcrop => smartcrop,
       } }
     else
       err -> err
@@ -143,6 +345,19 @@ defmodule Elxvips do
     end
   end
 
+  # vips expects scale as f64, this should convert integers to float
+  defp resize_opts( opts ) do
+    opts = case Keyword.get( opts, :scale ) do
+      scale when is_integer( scale ) -> Keyword.put( opts, :scale, scale / 1 )
+      _ -> opts
+    end
+
+    case Keyword.get( opts, :focal_point ) do
+      { x, y } when is_number( x ) and is_number( y ) -> Keyword.put( opts, :focal_point, { x / 1, y / 1 } )
+      _ -> opts
+    end
+  end
+
   # vips expects a vector of f64, this should convert integers to float
   defp background_opts( [ c1 ] ), do: [ c1 / 1 ]
   defp background_opts( [ c1, c2, c3 ] ), do: [ c1 / 1, c2 / 1, c3 / 1 ]
@@ -195,10 +410,41 @@ defmodule Elxvips do
   def background( { :ok, image_bytes = %ImageBytes{} }, colors ), do: background( image_bytes, colors )
 
   @doc """
-  Applies resize options to an %ImageFile{} or %ImageBytes{}, accepts :width, :height and :type (not implemented yet).
+  Applies resize options to an %ImageFile{} or %ImageBytes{}, accepts :width, :height and :resize_type.
   If no width or height is specified dimensions are calculated from the input image.
   Empty resize( no :width and no :height) will produce an image with the dimensions as the original one.
 
+  The `:resize_type` controls how the image is fitted in the width x height box:
+  * `:cover` - (default, same as `:auto`) preserves aspect ratio, crops the image to cover the whole box
+  * `:contain` - preserves aspect ratio, letterboxes the image into the box using the `background` color
+  * `:fill` - ignores aspect ratio, stretches the image to the box
+  * `:inside` - preserves aspect ratio, resizes the image to be as large as possible while fitting in the box
+  * `:outside` - preserves aspect ratio, resizes the image to be as small as possible while covering the box
+
+  The `:gravity` option controls which part of the image is kept when cropping with `:cover`,
+  and where the image is placed with `:contain`:
+  * `:centre` - (default) crops from the centre of the image
+  * `:attention`, `:entropy`, `:low`, `:high`, `:all` - libvips smartcrop strategies
+  * `:north`, `:north_east`, `:east`, `:south_east`, `:south`, `:south_west`, `:west`, `:north_west` - crops from a fixed side
+
+  When a smart crop is made, the resulting image carries the chosen rectangle and attention centre
+  in its `:smartcrop` field as an `%Elxvips.Smartcrop{}`, which can be turned into a `:focal_point`
+  to crop other sizes consistently.
+
+  The `:focal_point` option, a `{ x, y }` tuple relative to the image (`{ 0.5, 0.5 }` being the centre),
+  positions the `:cover` crop around that point instead of using `:gravity`, the crop window stays inside the image.
+
+  The `:kernel` option selects the resampling kernel, one of `:nearest`, `:linear`, `:cubic`, `:mitchell`,
+  `:lanczos2`, `:lanczos3` (default), `:mks2013` or `:mks2021`.
+  Setting `:linear` to `true` resizes in linear light (scRGB), which avoids darkening fine high-contrast patterns
+  at the cost of speed.
+
+  Constraints:
+  * `:scale` - resizes both dimensions by a factor, can't be combined with `:width`, `:height` or `:longest_edge`
+  * `:longest_edge` - resizes the longest edge of the image to this size, the other one follows the aspect ratio
+  * `:max_width`, `:max_height` - scales the output down until it fits these limits
+  * `:without_enlargement` - never upscales the image, shrinking the target box instead
+
   ## Examples
       iex> import Elxvips
       iex>
@@ -207,21 +453,390 @@ defmodule Elxvips do
       iex  |> to_bytes()
       {:ok, %ImageBytes{}}
 
+      iex> from_file( "test/input.png" )
+      iex> |> resize( width: 300, height: 300, resize_type: :contain )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+      iex> from_file( "test/input.png" )
+      iex> |> resize( width: 1200, height: 300, gravity: :north )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+      iex> from_file( "test/input.png" )
+      iex> |> resize( width: 300, height: 300, focal_point: { 0.25, 0.4 } )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+      iex> from_file( "test/input.png" )
+      iex> |> resize( longest_edge: 1200, without_enlargement: true )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
   """
   def resize( image_file, opts \\ [] )
   def resize( image_file = %ImageFile{}, opts ) do
     { :ok, %ImageFile{ image_file |
-      :resize => Kernel.struct( image_file.resize, opts )
+      :resize => Kernel.struct( image_file.resize, resize_opts( opts ) )
     } }
   end
   def resize( { :ok, image_file = %ImageFile{} }, opts ), do: resize( image_file, opts )
   def resize( image_bytes = %ImageBytes{}, opts ) do
     { :ok, %ImageBytes{ image_bytes |
-      :resize => Kernel.struct( image_bytes.resize, opts )
+      :resize => Kernel.struct( image_bytes.resize, resize_opts( opts ) )
     } }
   end
   def resize( { :ok, image_bytes = %ImageBytes{} }, opts ), do: resize( image_bytes, opts )
 
+  @doc """
+  Crops a rectangle out of an %ImageFile{} or %ImageBytes{}, accepts :left, :top, :width, :height and :stage.
+  By default the crop is applied to the original image, before resizing, use `stage: :after` to crop the resized image instead.
+  The rectangle must be inside the image, otherwise processing returns an error.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> crop( left: 10, top: 20, width: 300, height: 200 )
+      iex> |> resize( width: 150 )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def crop( image, opts \\ [] )
+  def crop( image_file = %ImageFile{}, opts ) do
+    { :ok, %ImageFile{ image_file |
+      :crop => Kernel.struct( %CropOptions{}, opts )
+    } }
+  end
+  def crop( image_bytes = %ImageBytes{}, opts ) do
+    { :ok, %ImageBytes{ image_bytes |
+      :crop => Kernel.struct( %CropOptions{}, opts )
+    } }
+  end
+  def crop( { :ok, image }, opts ), do: crop( image, opts )
+
+  @doc """
+  Rotates an %ImageFile{} or %ImageBytes{} clockwise by an angle in degrees, before resizing.
+  Multiples of 90 are lossless, any other angle enlarges the image and fills the corners with the background,
+  a list of 1 or 3 numbers, black or transparent by default.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> rotate( 90 )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+      iex> from_file( "test/input.png" )
+      iex> |> rotate( 30, [ 255, 255, 255 ] )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def rotate( image, angle, background \\ [] )
+  def rotate( image_file = %ImageFile{}, angle, background ) when is_number( angle ) do
+    with background_list when is_list( background_list ) <- background_opts( background ) do
+      { :ok, %ImageFile{ image_file |
+        :rotate => %RotateOptions{ angle: angle / 1, background: background_list }
+      } }
+    end
+  end
+  def rotate( image_bytes = %ImageBytes{}, angle, background ) when is_number( angle ) do
+    with background_list when is_list( background_list ) <- background_opts( background ) do
+      { :ok, %ImageBytes{ image_bytes |
+        :rotate => %RotateOptions{ angle: angle / 1, background: background_list }
+      } }
+    end
+  end
+  def rotate( { :ok, image }, angle, background ), do: rotate( image, angle, background )
+
+  @doc """
+  Mirrors an %ImageFile{} or %ImageBytes{}, `:horizontal` (default) flips left to right, `:vertical` flips top to bottom.
+  Both can be applied to the same image.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> flip( :vertical )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def flip( image, direction \\ :horizontal )
+  def flip( image_file = %ImageFile{}, :horizontal ), do: { :ok, %ImageFile{ image_file | :flip_horizontal => true } }
+  def flip( image_file = %ImageFile{}, :vertical ), do: { :ok, %ImageFile{ image_file | :flip_vertical => true } }
+  def flip( image_bytes = %ImageBytes{}, :horizontal ), do: { :ok, %ImageBytes{ image_bytes | :flip_horizontal => true } }
+  def flip( image_bytes = %ImageBytes{}, :vertical ), do: { :ok, %ImageBytes{ image_bytes | :flip_vertical => true } }
+  def flip( { :ok, image }, direction ), do: flip( image, direction )
+
+  @doc """
+  Places the resized %ImageFile{} or %ImageBytes{} on a canvas of an exact size, accepts :width, :height, :gravity and :background.
+  The `:gravity` sets where the image lands on the canvas, `:centre` (default), `:north`, `:north_east`, `:east`, `:south_east`,
+  `:south`, `:south_west`, `:west` or `:north_west`. The canvas is filled with `:background`, a list of 1 or 3 numbers,
+  falling back to the background of the save options, then to black or transparent.
+  An image larger than the canvas is cropped.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> resize( width: 1200, height: 630, resize_type: :inside )
+      iex> |> embed( width: 1200, height: 630, background: [ 255, 255, 255 ] )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def embed( image, opts \\ [] )
+  def embed( image_file = %ImageFile{}, opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageFile{ image_file |
+        :embed => Kernel.struct( %EmbedOptions{}, opts )
+      } }
+    end
+  end
+  def embed( image_bytes = %ImageBytes{}, opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageBytes{ image_bytes |
+        :embed => Kernel.struct( %EmbedOptions{}, opts )
+      } }
+    end
+  end
+  def embed( { :ok, image }, opts ), do: embed( image, opts )
+
+  @doc """
+  Removes the borders of %ImageFile{} or %ImageBytes{} before it gets resized, accepts :threshold and :background.
+  Pixels differing from `:background` by less than `:threshold` (default 10) are treated as border.
+  The `:background` is a list of 1 or 3 numbers, by default the colour of the top-left pixel is used,
+  transparent borders are trimmed as well. An image made only of background is left untouched.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> trim( threshold: 20 )
+      iex> |> resize( width: 100 )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def trim( image, opts \\ [] )
+  def trim( image_file = %ImageFile{}, opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageFile{ image_file |
+        :trim => Kernel.struct( %TrimOptions{}, trim_opts( opts ) )
+      } }
+    end
+  end
+  def trim( image_bytes = %ImageBytes{}, opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageBytes{ image_bytes |
+        :trim => Kernel.struct( %TrimOptions{}, trim_opts( opts ) )
+      } }
+    end
+  end
+  def trim( { :ok, image }, opts ), do: trim( image, opts )
+
+  defp trim_opts( opts ) do
+    case Keyword.get( opts, :threshold ) do
+      threshold when is_integer( threshold ) -> Keyword.put( opts, :threshold, threshold / 1 )
+      _ -> opts
+    end
+  end
+
+  @doc """
+  Applies an affine transform to %ImageFile{} or %ImageBytes{} before it gets resized, `matrix` being `[ a, b, c, d ]`
+  so that `x' = a * x + b * y + odx` and `y' = c * x + d * y + ody`.
+  Accepts `:odx`, `:ody`, `:idx`, `:idy` for output and input displacements, `:interpolate`, one of `:nearest`,
+  `:bilinear` (default), `:bicubic`, `:lbb`, `:nohalo` or `:vsqbs`, and `:background`, a list of 1 or 3 numbers.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> affine( [ 1, 0.2, 0, 1 ], interpolate: :bicubic )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def affine( image, matrix, opts \\ [] )
+  def affine( image_file = %ImageFile{}, matrix, opts ) when is_list( matrix ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageFile{ image_file |
+        :affine => Kernel.struct( %AffineOptions{}, [ { :matrix, Enum.map( matrix, &( &1 / 1 ) ) } | float_opts( opts, [ :odx, :ody, :idx, :idy ] ) ] )
+      } }
+    end
+  end
+  def affine( image_bytes = %ImageBytes{}, matrix, opts ) when is_list( matrix ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageBytes{ image_bytes |
+        :affine => Kernel.struct( %AffineOptions{}, [ { :matrix, Enum.map( matrix, &( &1 / 1 ) ) } | float_opts( opts, [ :odx, :ody, :idx, :idy ] ) ] )
+      } }
+    end
+  end
+  def affine( { :ok, image }, matrix, opts ), do: affine( image, matrix, opts )
+
+  @doc """
+  Straightens a quadrilateral of %ImageFile{} or %ImageBytes{} into a rectangle, before it gets resized.
+  `corners` lists the source points as `{ x, y }` in the order top-left, top-right, bottom-right, bottom-left.
+  The output size defaults to the longest edges of the quadrilateral and can be set with `:width` and `:height`.
+  Also accepts `:interpolate` and `:background` as `affine/3` does.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> perspective( [ { 40, 30 }, { 600, 10 }, { 630, 470 }, { 20, 440 } ], width: 600, height: 450 )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def perspective( image, corners, opts \\ [] )
+  def perspective( image_file = %ImageFile{}, corners, opts ) when is_list( corners ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageFile{ image_file |
+        :perspective => Kernel.struct( %PerspectiveOptions{}, [ { :corners, corners_opts( corners ) } | opts ] )
+      } }
+    end
+  end
+  def perspective( image_bytes = %ImageBytes{}, corners, opts ) when is_list( corners ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :ok, %ImageBytes{ image_bytes |
+        :perspective => Kernel.struct( %PerspectiveOptions{}, [ { :corners, corners_opts( corners ) } | opts ] )
+      } }
+    end
+  end
+  def perspective( { :ok, image }, corners, opts ), do: perspective( image, corners, opts )
+
+  defp corners_opts( corners ), do: Enum.map( corners, fn { x, y } -> { x / 1, y / 1 } end )
+
+  defp float_opts( opts, keys ) do
+    Enum.map( opts, fn
+      { key, value } when is_integer( value ) -> if key in keys, do: { key, value / 1 }, else: { key, value }
+      opt -> opt
+    end )
+  end
+
+  @doc """
+  Appends operations to %ImageFile{} or %ImageBytes{}, they run in the given order once the options set by
+  `crop/2`, `trim/2`, `rotate/3`, `resize/2` and the others are applied. Those options are a shorthand for the
+  same operations in a fixed order, leave them out to order every step here, e.g. rotating before resizing.
+  The overlay of `:composite` is loaded with the limits of the image, see `limits/2`. Supported operations:
+  * `{ :resize, opts }` - takes the options of `resize/2`
+  * `{ :crop, opts }` - takes `:left`, `:top`, `:width` and `:height`
+  * `{ :trim, opts }` - takes the options of `trim/2`
+  * `{ :rotate, angle }` or `{ :rotate, opts }` - takes `:angle` and `:background`
+  * `{ :flip, :horizontal | :vertical }`
+  * `{ :embed, opts }` - takes the options of `embed/2`
+  * `{ :affine, opts }` - takes `:matrix` along with the options of `affine/3`
+  * `{ :perspective, opts }` - takes `:corners` along with the options of `perspective/3`
+  * `{ :blur, sigma }` - gaussian blur
+  * `{ :composite, opts }` - blends `:image`, an %ImageFile{} or %ImageBytes{}, over the image, placed by `:gravity`
+    (`:north_west` by default) and moved by `:left` and `:top`, with `:blend` being `:over` (default), `:add`,
+    `:saturate`, `:multiply`, `:screen`, `:overlay`, `:darken`, `:lighten`, `:colour_dodge`, `:colour_burn`,
+    `:hard_light`, `:soft_light`, `:difference` or `:exclusion`. The overlay is used as it is, an overlay with
+    processing options of its own is refused, process it with `to_bytes/1` first
+
+  A failing operation is reported with its index and name, as in `{:error, "operation 1 (crop) failed: ..."}`.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> operations( [
+      iex>   resize: [ width: 300 ],
+      iex>   blur: 1.5,
+      iex>   composite: [ image: %ImageFile{ path: "test/input2.png" }, gravity: :south_east, left: -10, top: -10 ],
+      iex> ] )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def operations( image_file = %ImageFile{}, ops ) when is_list( ops ) do
+    with { :ok, ops } <- build_operations( ops ) do
+      { :ok, %ImageFile{ image_file | :operations => image_file.operations ++ ops } }
+    end
+  end
+  def operations( image_bytes = %ImageBytes{}, ops ) when is_list( ops ) do
+    with { :ok, ops } <- build_operations( ops ) do
+      { :ok, %ImageBytes{ image_bytes | :operations => image_bytes.operations ++ ops } }
+    end
+  end
+  def operations( { :ok, image }, ops ), do: operations( image, ops )
+
+  defp build_operations( ops ) do
+    ops
+    |> Enum.reduce_while( { :ok, [] }, fn op, { :ok, acc } ->
+      case build_operation( op ) do
+        { :error, _ } = err -> { :halt, err }
+        op -> { :cont, { :ok, [ op | acc ] } }
+      end
+    end )
+    |> case do
+      { :ok, ops } -> { :ok, Enum.reverse( ops ) }
+      err -> err
+    end
+  end
+
+  defp build_operation( { :resize, opts } ) when is_list( opts ) do
+    { :resize, Kernel.struct( %Elxvips.ResizeOptions{ :width => 0, :height => 0 }, resize_opts( opts ) ) }
+  end
+  defp build_operation( { :crop, opts } ) when is_list( opts ), do: { :crop, Kernel.struct( %CropOptions{}, opts ) }
+  defp build_operation( { :trim, opts } ) when is_list( opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :trim, Kernel.struct( %TrimOptions{}, trim_opts( opts ) ) }
+    end
+  end
+  defp build_operation( { :rotate, angle } ) when is_number( angle ), do: build_operation( { :rotate, [ angle: angle ] } )
+  defp build_operation( { :rotate, opts } ) when is_list( opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :rotate, Kernel.struct( %RotateOptions{}, float_opts( opts, [ :angle ] ) ) }
+    end
+  end
+  defp build_operation( { :flip, direction } ) when direction in [ :horizontal, :vertical ], do: { :flip, direction }
+  defp build_operation( { :embed, opts } ) when is_list( opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      { :embed, Kernel.struct( %EmbedOptions{}, opts ) }
+    end
+  end
+  defp build_operation( { :affine, opts } ) when is_list( opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      matrix = opts |> Keyword.get( :matrix, [] ) |> Enum.map( &( &1 / 1 ) )
+      { :affine, Kernel.struct( %AffineOptions{}, Keyword.put( float_opts( opts, [ :odx, :ody, :idx, :idy ] ), :matrix, matrix ) ) }
+    end
+  end
+  defp build_operation( { :perspective, opts } ) when is_list( opts ) do
+    with opts when is_list( opts ) <- check_opts( opts ) do
+      corners = opts |> Keyword.get( :corners, [] ) |> corners_opts()
+      { :perspective, Kernel.struct( %PerspectiveOptions{}, Keyword.put( opts, :corners, corners ) ) }
+    end
+  end
+  defp build_operation( { :blur, sigma } ) when is_number( sigma ), do: { :blur, %BlurOptions{ :sigma => sigma / 1 } }
+  defp build_operation( { :composite, opts } ) when is_list( opts ) do
+    with source when is_list( source ) <- composite_source( Keyword.get( opts, :image ) ) do
+      { :composite, Kernel.struct( %CompositeOptions{}, Keyword.delete( opts, :image ) ++ source ) }
+    end
+  end
+  defp build_operation( { name, _ } ), do: { :error, "operation #{ inspect( name ) } not supported" }
+  defp build_operation( _ ), do: { :error, "operations are { name, options } tuples" }
+
+  # only the source of the overlay is loaded, any other option it carries would be silently dropped
+  defp composite_source( overlay = %ImageFile{ :path => path } ) when is_binary( path ) do
+    overlay_source( overlay, %ImageFile{ :path => path, :smartcrop => overlay.smartcrop }, [ path: path ] )
+  end
+  defp composite_source( overlay = %ImageBytes{ :bytes => bytes } ) when is_bitstring( bytes ) do
+    overlay_source( overlay, %ImageBytes{ :bytes => bytes, :smartcrop => overlay.smartcrop }, [ bytes: bytes ] )
+  end
+  defp composite_source( _ ), do: { :error, "composite needs an :image, either %ImageFile{} or %ImageBytes{}" }
+
+  defp overlay_source( overlay, overlay, source ), do: source
+  defp overlay_source( overlay, plain, _ ) do
+    options = for { key, value } <- Map.from_struct( overlay ), Map.get( plain, key ) != value, do: key
+    { :error, "composite overlay can't have processing options, got: #{ inspect( options ) }" }
+  end
+
   @save_opts_default [ quality: 100, strip: true, compression: 6, background: [] ]
 
   @jpg_default_opts Keyword.merge( @save_opts_default, [ quality: 90 ] )
@@ -437,6 +1052,59 @@ defmodule Elxvips do
   def to_bytes( image = %ImageBytes{} ), do: process_to_bytes( image )
   def to_bytes( { :ok, image } ), do: to_bytes( image )
 
+  @doc """
+  Decodes the image once and encodes it into several variants, each spec taking `:resize` with the options of `resize/2`,
+  `:format` (`:jpg` by default, `:png`, `:webp`, `:avif` or `:svg`) and the options of the matching format function.
+  Everything but the resize and save options is applied once and shared by the variants.
+  Returns the variants as `{:ok, %ImageBytes{}}` or `{:error, reason}`, in the order of the specs.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> to_variants( [
+      iex>   [ resize: [ width: 320 ], format: :webp, quality: 80 ],
+      iex>   [ resize: [ width: 640 ], format: :jpg ],
+      iex> ] )
+      {:ok, [ {:ok, %ImageBytes{}}, {:ok, %ImageBytes{}} ]}
+  """
+  def to_variants( image_file = %ImageFile{}, specs ) when is_list( specs ) do
+    with { :ok, variants } <- build_variants( image_file, specs ),
+         { :ok, results } <- Elxvips.Native.vips_process_file_variants( image_file, variants ) do
+      { :ok, Enum.map( results, &variant_result/1 ) }
+    end
+  end
+  def to_variants( image_bytes = %ImageBytes{}, specs ) when is_list( specs ) do
+    with { :ok, variants } <- build_variants( image_bytes, specs ),
+         { :ok, results } <- Elxvips.Native.vips_process_bytes_variants( image_bytes, variants ) do
+      { :ok, Enum.map( results, &variant_result/1 ) }
+    end
+  end
+  def to_variants( { :ok, image }, specs ), do: to_variants( image, specs )
+
+  defp build_variants( image, specs ) do
+    specs
+    |> Enum.reduce_while( { :ok, [] }, fn spec, { :ok, acc } ->
+      { format, spec } = Keyword.pop( spec, :format, :jpg )
+      { resize_spec, save_spec } = Keyword.pop( spec, :resize, [] )
+
+      with true <- format in [ :jpg, :png, :webp, :avif, :svg ] || { :error, "format #{ inspect( format ) } not supported" },
+           { :ok, resized } <- resize( image, resize_spec ),
+           { :ok, variant } <- apply( Elxvips, format, [ resized, save_spec ] ) do
+        { :cont, { :ok, [ %Variant{ :resize => variant.resize, :save => variant.save } | acc ] } }
+      else
+        err -> { :halt, err }
+      end
+    end )
+    |> case do
+      { :ok, variants } -> { :ok, Enum.reverse( variants ) }
+      err -> err
+    end
+  end
+
+  defp variant_result( { :ok, bytes, smartcrop } ), do: { :ok, %ImageBytes{ :bytes => bytes, :smartcrop => smartcrop } }
+  defp variant_result( err ), do: err
+
   @doc """
   Will save the image to a path on disk and return a new %ImageFile{} from the new path.
 
@@ -452,12 +1120,422 @@ defmodule Elxvips do
   def to_file( image = %ImageBytes{}, path ) when is_binary( path ), do: process_to_file( image, path )
   def to_file( { :ok, image }, path ), do: to_file( image, path )
 
+  @doc """
+  Queues the processing of the image on a pool of native workers and returns a job right away,
+  the calling process then receives `{job, {:ok, bytes}}` or `{job, {:error, reason}}`.
+  The job is cancelled when the calling process exits, see `await/2` and `cancel/1`.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> { :ok, job } = from_file( "test/input.png" ) |> resize( width: 300 ) |> png() |> to_bytes_async()
+      iex> await( job )
+      {:ok, <<137, 80, 78, 71, ...>>}
+  """
+  def to_bytes_async( image = %ImageFile{} ), do: Elxvips.Native.vips_process_async( image, :bytes )
+  def to_bytes_async( image = %ImageBytes{} ), do: Elxvips.Native.vips_process_async( image, :bytes )
+  def to_bytes_async( { :ok, image } ), do: to_bytes_async( image )
+
+  @doc """
+  Same as `to_bytes_async/1` but saves the image to `path`, the calling process receives `{job, {:ok, path}}`.
+  """
+  def to_file_async( image = %ImageFile{}, path ) when is_binary( path ) do
+    Elxvips.Native.vips_process_async( %ImageFile{ image | :save => Kernel.struct( image.save, [ path: path ] ) }, :file )
+  end
+  def to_file_async( image = %ImageBytes{}, path ) when is_binary( path ) do
+    Elxvips.Native.vips_process_async( %ImageBytes{ image | :save => Kernel.struct( image.save, [ path: path ] ) }, :file )
+  end
+  def to_file_async( { :ok, image }, path ), do: to_file_async( image, path )
+
+  @doc """
+  Waits for the result of an async job, returns `{:error, :timeout}` when it doesn't arrive in time,
+  the job is then cancelled and its result never arrives.
+  """
+  def await( job, timeout \\ :infinity ) when is_reference( job ) do
+    receive do
+      { ^job, result } -> result
+    after
+      timeout ->
+        cancel( job )
+        { :error, :timeout }
+    end
+  end
+
+  @doc """
+  Stops an async job, libvips aborts the running computation. Like `Task.shutdown/1`, a result already sent
+  is flushed from the mailbox and no `{job, _}` message arrives once it returns.
+  """
+  def cancel( job ) when is_reference( job ) do
+    :ok = Elxvips.Native.vips_cancel( job )
+    receive do
+      { ^job, _ } -> :ok
+    after
+      0 -> :ok
+    end
+  end
+
+  @doc """
+  Processes a list of independent images in parallel on a pool of native threads and returns their results in the same order,
+  `{:ok, bytes}` for an image and `{:ok, path}` for an `{ image, path }` tuple, or `{:error, reason}` for the jobs that failed.
+  The calling process only waits for the batch, which is cancelled when it exits.
+  Accepts the following options:
+  * `:width` - number of images processed at once, default is the number of schedulers online. All batches share
+    one pool of native threads, sized by the `VIPS_BATCH_WORKERS` environment variable (the number of cores by default),
+    so concurrent batches never run more images than that together
+  * `:timeout` - milliseconds to wait for the whole batch before cancelling it and returning `{:error, :timeout}`, default is `:infinity`
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> process_batch( [
+      iex>   from_file( "test/input.png" ) |> resize( width: 100 ) |> png(),
+      iex>   { from_file( "test/input.png" ) |> resize( width: 200 ) |> jpg(), "test/batch.jpg" },
+      iex>   from_file( "test/missing.png" ),
+      iex> ], width: 4 )
+      {:ok, [ {:ok, <<137, 80, 78, 71, ...>>}, {:ok, "test/batch.jpg"}, {:error, "failed to open image: ..."} ]}
+  """
+  def process_batch( images, opts \\ [] ) when is_list( images ) and is_list( opts ) do
+    width = Keyword.get( opts, :width, System.schedulers_online() )
+    timeout = Keyword.get( opts, :timeout, :infinity )
+
+    with true <- ( is_integer( width ) and width > 0 ) || { :error, "width must be a positive integer" },
+         { :ok, jobs } <- batch_jobs( images, [] ),
+         { :ok, batch } <- Elxvips.Native.vips_process_batch( jobs, width ) do
+      receive do
+        { ^batch, result } -> result
+      after
+        timeout ->
+          Elxvips.Native.vips_cancel_batch( batch )
+          # nothing is sent once the batch is cancelled, results sent just before are flushed
+          receive do
+            { ^batch, _ } -> { :error, :timeout }
+          after
+            0 -> { :error, :timeout }
+          end
+      end
+    end
+  end
+
+  defp batch_jobs( [], jobs ), do: { :ok, Enum.reverse( jobs ) }
+  defp batch_jobs( [ image | images ], jobs ) do
+    case batch_job( image ) do
+      { :error, reason } -> { :error, reason }
+      job -> batch_jobs( images, [ job | jobs ] )
+    end
+  end
+
+  defp batch_job( { :ok, image } ), do: batch_job( image )
+  defp batch_job( image = %ImageFile{} ), do: { image, :bytes }
+  defp batch_job( image = %ImageBytes{} ), do: { image, :bytes }
+  defp batch_job( { { :ok, image }, path } ), do: batch_job( { image, path } )
+  defp batch_job( { image = %ImageFile{}, path } ) when is_binary( path ) do
+    { %ImageFile{ image | :save => Kernel.struct( image.save, [ path: path ] ) }, :file }
+  end
+  defp batch_job( { image = %ImageBytes{}, path } ) when is_binary( path ) do
+    { %ImageBytes{ image | :save => Kernel.struct( image.save, [ path: path ] ) }, :file }
+  end
+  defp batch_job( _ ), do: { :error, "batch jobs are images or { image, path } tuples" }
+
+  @doc """
+  Creates an %ImageBytes{} struct reading the image from an enumerable of binaries (a file or socket stream),
+  the chunks are pulled only as the decoder needs them when the image is processed with `to_stream/1`.
+  Formats that need random access, like TIFF, are buffered by libvips as they are read.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> File.stream!( "/path/input.tif", [], 65_536 )
+      iex> |> from_stream()
+      iex> |> resize( width: 1200 )
+      iex> |> jpg()
+      iex> |> to_stream()
+      iex> |> Stream.into( File.stream!( "/path/output.jpg" ) )
+      iex> |> Stream.run()
+      :ok
+  """
+  def from_stream( input ) do
+    if Enumerable.impl_for( input ) do
+      { :ok, %ImageBytes{ :bytes => <<>>, :stream => input } }
+    else
+      { :error, "input must be an enumerable of binaries" }
+    end
+  end
+
+  @doc """
+  Processes the image on the native workers and returns a stream emitting the encoded image in chunks,
+  as libvips produces them, so the whole output is never held in memory. Input of `from_stream/1` is
+  read lazily as well. Processing starts once the stream is run and is cancelled when it is halted,
+  a failure raises when the stream is consumed. SVG output can not be streamed.
+  Streams run on a pool of native threads of their own, sized by the `VIPS_STREAM_WORKERS` environment variable
+  (the number of cores by default), streams started beyond it wait for a thread to be free.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" ) |> resize( width: 300 ) |> webp() |> to_stream() |> Enum.into( <<>> )
+      <<82, 73, 70, 70, ...>>
+  """
+  def to_stream( image = %ImageFile{} ), do: output_stream( image, nil )
+  def to_stream( image = %ImageBytes{ :stream => nil } ), do: output_stream( image, nil )
+  def to_stream( image = %ImageBytes{ :stream => input } ), do: output_stream( %ImageBytes{ image | :stream => nil }, input )
+  def to_stream( { :ok, image } ), do: to_stream( image )
+
+  defp output_stream( image, input ) do
+    Stream.resource(
+      fn ->
+        case Elxvips.Native.vips_stream_process( image, input != nil ) do
+          { :ok, stream } -> { stream, input && fn acc -> Enumerable.reduce( input, acc, fn chunk, _ -> { :suspend, chunk } end ) end }
+          { :error, reason } -> raise RuntimeError, message: stream_error( reason )
+        end
+      end,
+      &next_chunk/1,
+      fn { stream, input } ->
+        Elxvips.Native.vips_stream_cancel( stream )
+        flush_stream( stream )
+        if input, do: input.( { :halt, nil } )
+      end
+    )
+  end
+
+  # nothing arrives once the stream is cancelled, messages sent before are dropped
+  defp flush_stream( stream ) do
+    receive do
+      { ^stream, _ } -> flush_stream( stream )
+    after
+      0 -> :ok
+    end
+  end
+
+  defp next_chunk( state = { stream, input } ) do
+    receive do
+      { ^stream, :more } -> { [], { stream, feed_chunk( stream, input ) } }
+      { ^stream, { :chunk, bytes } } -> { [ bytes ], state }
+      { ^stream, :done } -> { :halt, state }
+      { ^stream, { :error, reason } } -> raise RuntimeError, message: stream_error( reason )
+    end
+  end
+
+  # limits and timeouts fail with a term rather than a message
+  defp stream_error( reason ) when is_binary( reason ), do: reason
+  defp stream_error( reason ), do: inspect( reason )
+
+  # answers a request of the decoder with the next chunk of the input, the continuation is nil once it is exhausted
+  defp feed_chunk( stream, nil ) do
+    Elxvips.Native.vips_stream_close( stream )
+    nil
+  end
+  defp feed_chunk( stream, input ) do
+    case input.( { :cont, nil } ) do
+      { :suspended, chunk, next } ->
+        Elxvips.Native.vips_stream_write( stream, IO.iodata_to_binary( chunk ) )
+        next
+      _ -> feed_chunk( stream, nil )
+    end
+  end
+
   def set_concurrency( concurrency ) when is_integer( concurrency ) do
     Elxvips.Native.set_concurrency( concurrency )
   end
 
+  @doc """
+  Sets the limits of the libvips operation cache, limits not given keep their current value.
+  `:max` is the number of operations, `:max_mem` the memory they may hold in bytes and `:max_files` the files they may keep open.
+  `:max` and `:max_files` go from 0 to 2147483647, an `{ :error, reason }` is returned and nothing is changed when a limit is out of range.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> set_cache( max: 100, max_mem: 64 * 1024 * 1024, max_files: 20 )
+      :ok
+  """
+  def set_cache( opts ) when is_list( opts ) do
+    options = Kernel.struct( %CacheOptions{}, opts )
+
+    # the range of each limit is checked by the NIF, which sets none of them when one is out of range
+    if Enum.all?( [ options.max, options.max_mem, options.max_files ], &( &1 == nil or is_integer( &1 ) ) ) do
+      Elxvips.Native.set_cache( options )
+    else
+      { :error, "cache limits must be integers" }
+    end
+  end
+
+  @doc """
+  Drops every operation held by the libvips cache, releasing the memory and files they keep.
+  """
+  def drop_cache(), do: Elxvips.Native.drop_cache()
+
+  @doc """
+  Returns the memory libvips is using right now as %Elxvips.MemoryStats{}: tracked memory, its high-water mark,
+  allocations and open files, along with the size and limits of the operation cache.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> get_memory_stats()
+      {:ok, %Elxvips.MemoryStats{ mem: 0, mem_highwater: 71_663_616, allocs: 0, files: 0, cache_size: 12, ... }}
+  """
+  def get_memory_stats(), do: Elxvips.Native.get_memory_stats()
+
+  @doc """
+  Blocks libvips operations at runtime, loaders included, so untrusted input can only be opened by the loaders left allowed.
+  Accepts the following options:
+  * `:block_untrusted` - blocks, or unblocks with `false`, the operations libvips flags as untrusted, such as the ImageMagick, matload and FITS loaders
+  * `:block` - operations to block, by nickname or class name, a class blocks all its subclasses
+  * `:allow` - operations to unblock, applied last so part of a blocked class can be reopened
+
+  Nothing is changed if an operation is not found. The same options are applied when the application starts with
+  `config :elxvips, :block_options, block_untrusted: true`, read at runtime so `config/runtime.exs` can set them,
+  the application fails to start when they are invalid.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> set_block( block: [ "VipsForeignLoad" ], allow: [ "VipsForeignLoadJpeg", "VipsForeignLoadPng" ] )
+      :ok
+  """
+  def set_block( opts ) when is_list( opts ) do
+    options = Kernel.struct( %BlockOptions{}, opts )
+
+    cond do
+      options.block_untrusted not in [ nil, true, false ] ->
+        { :error, "block_untrusted must be a boolean" }
+      not Enum.all?( options.block ++ options.allow, &is_binary/1 ) ->
+        { :error, "operations are named by strings" }
+      true ->
+        Elxvips.Native.set_block( options )
+    end
+  end
+
+  @doc """
+  Returns the nicknames of the loaders that are not blocked.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> get_allowed_loaders()
+      {:ok, [ "csvload_source", "matrixload_source", ..., "jpegload", "jpegload_buffer", "jpegload_source", ... ]}
+  """
+  def get_allowed_loaders(), do: Elxvips.Native.get_allowed_loaders()
+
+  @doc """
+  Aborts the processing once it has been running for `timeout_ms` milliseconds, which then returns `{:error, :timeout}`.
+  The deadline counts from the start of the processing, libvips is stopped at the next tile it computes,
+  whether it is encoding or computing a step before it such as a smart crop or a trim. It holds for `load/1`,
+  `get_trim_box/1`, `to_variants/2`, async and batch jobs, and streams waiting for their input as well.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/8k.jpg" )
+      iex> |> resize( width: 4000 )
+      iex> |> timeout( 1 )
+      iex> |> to_bytes()
+      {:error, :timeout}
+  """
+  def timeout( image, timeout_ms )
+  def timeout( image_file = %ImageFile{}, timeout_ms ) when is_integer( timeout_ms ) and timeout_ms >= 0 do
+    { :ok, %ImageFile{ image_file | :timeout_ms => timeout_ms } }
+  end
+  def timeout( image_bytes = %ImageBytes{}, timeout_ms ) when is_integer( timeout_ms ) and timeout_ms >= 0 do
+    { :ok, %ImageBytes{ image_bytes | :timeout_ms => timeout_ms } }
+  end
+  def timeout( { :ok, image }, timeout_ms ), do: timeout( image, timeout_ms )
+
+  @doc """
+  Sends `{:elxvips_progress, tag, percent}` messages to `:pid`, the calling process by default, while the image is encoded.
+  A message is sent whenever the percentage changes and 100 once the image is done, `:tag` tells images apart.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> progress( tag: "input.png" )
+      iex> |> to_bytes()
+      iex> flush()
+      {:elxvips_progress, "input.png", 100}
+  """
+  def progress( image, opts \\ [] )
+  def progress( image_file = %ImageFile{}, opts ) when is_list( opts ) do
+    { :ok, %ImageFile{ image_file | :progress => progress_opts( opts ) } }
+  end
+  def progress( image_bytes = %ImageBytes{}, opts ) when is_list( opts ) do
+    { :ok, %ImageBytes{ image_bytes | :progress => progress_opts( opts ) } }
+  end
+  def progress( { :ok, image }, opts ), do: progress( image, opts )
+
+  defp progress_opts( opts ), do: Kernel.struct( %ProgressOptions{ :pid => self() }, opts )
+
+  @doc """
+  Refuses to decode images over the given limits, checked against the input size and the image header before any pixels are decoded.
+  Accepts the following options, all unset by default:
+  * `:max_pixels` - maximum width times height
+  * `:max_width` - maximum width in pixels
+  * `:max_height` - maximum height in pixels
+  * `:max_pages` - maximum number of pages or frames
+  * `:max_bytes` - maximum size of the encoded input
+  * `:fail_on` - `:none`, `:truncated`, `:error` or `:warning`, fails the load on damaged input of at least this severity, default is `:none`
+
+  A violation returns `{:error, {:limit_exceeded, limit, value, max}}`.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> limits( max_width: 100 )
+      iex> |> to_bytes()
+      {:error, {:limit_exceeded, :max_width, 640, 100}}
+  """
+  def limits( image, opts \\ [] )
+  def limits( image_file = %ImageFile{}, opts ) when is_list( opts ) do
+    with { :ok, limits } <- limits_opts( opts ) do
+      { :ok, %ImageFile{ image_file | :limits => limits } }
+    end
+  end
+  def limits( image_bytes = %ImageBytes{}, opts ) when is_list( opts ) do
+    with { :ok, limits } <- limits_opts( opts ) do
+      { :ok, %ImageBytes{ image_bytes | :limits => limits } }
+    end
+  end
+  def limits( { :ok, image }, opts ), do: limits( image, opts )
+
+  defp limits_opts( opts ) do
+    limits = Kernel.struct( %LoadLimits{}, opts )
+    max_values = [ limits.max_pixels, limits.max_width, limits.max_height, limits.max_pages, limits.max_bytes ]
+    cond do
+      not Enum.all?( max_values, &( is_nil( &1 ) or ( is_integer( &1 ) and &1 >= 0 ) ) ) ->
+        { :error, "limits must be non negative integers" }
+      limits.fail_on not in [ :none, :truncated, :error, :warning ] ->
+        { :error, "fail_on can be :none, :truncated, :error or :warning" }
+      true ->
+        { :ok, limits }
+    end
+  end
+
+  @doc """
+  Enables or disables rotating the image upright according to its EXIF orientation tag, enabled by default.
+  The orientation tag is removed from rotated images, so it is not applied twice when metadata is kept.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/photo.jpg" )
+      iex> |> autorotate( false )
+      iex  |> to_bytes()
+      {:ok, %ImageBytes{}}
+
+  """
+  def autorotate( image, enabled \\ true )
+  def autorotate( image_file = %ImageFile{}, enabled ) when is_boolean( enabled ) do
+    { :ok, %ImageFile{ image_file | :autorotate => enabled } }
+  end
+  def autorotate( image_bytes = %ImageBytes{}, enabled ) when is_boolean( enabled ) do
+    { :ok, %ImageBytes{ image_bytes | :autorotate => enabled } }
+  end
+  def autorotate( { :ok, image }, enabled ), do: autorotate( image, enabled )
+
   @doc """
   Returns dimensions of the specified image, works with a image path or bytes.
+  Dimensions are reported after applying the EXIF orientation, unless autorotate is disabled for the image.
 
   ## Examples
       iex> import Elxvips
@@ -466,12 +1544,54 @@ defmodule Elxvips do
       iex> |> get_image_sizes()
       {:ok, [640, 486]}
   """
-  def get_image_sizes( %ImageFile{ :path => path } ), do: Elxvips.Native.vips_get_image_sizes( path )
+  def get_image_sizes( %{ :handle => handle } ) when is_reference( handle ), do: Elxvips.Native.vips_get_handle_sizes( handle )
+  def get_image_sizes( %ImageFile{ :path => path, :autorotate => autorotate } ), do: Elxvips.Native.vips_get_image_sizes_autorotate( path, autorotate )
   def get_image_sizes( {:ok, image_file = %ImageFile{ :path => path } } ) when is_binary( path ), do: get_image_sizes( image_file )
 
-  def get_image_sizes( %ImageBytes{ :bytes => bytes } ) when is_bitstring( bytes ), do: Elxvips.Native.vips_get_image_bytes_sizes( bytes )
+  def get_image_sizes( %ImageBytes{ :bytes => bytes, :autorotate => autorotate } ) when is_bitstring( bytes ), do: Elxvips.Native.vips_get_image_bytes_sizes_autorotate( bytes, autorotate )
   def get_image_sizes( { :ok, image_bytes = %ImageBytes{} } ), do: get_image_sizes( image_bytes )
 
+  @doc """
+  Returns the box `trim/2` would keep as `[ left, top, width, height ]`, relative to the loaded (and autorotated) image.
+  Uses the options of `trim/2` when set, the defaults otherwise. Width and height are 0 when the image is only background.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> from_file( "test/input.png" )
+      iex> |> get_trim_box()
+      {:ok, [0, 0, 640, 486]}
+  """
+  def get_trim_box( image_file = %ImageFile{} ), do: Elxvips.Native.vips_get_file_trim_box( image_file )
+  def get_trim_box( image_bytes = %ImageBytes{} ), do: Elxvips.Native.vips_get_bytes_trim_box( image_bytes )
+  def get_trim_box( { :ok, image } ), do: get_trim_box( image )
+
+  @doc """
+  Decodes %ImageFile{} or %ImageBytes{} once and keeps the pixels in memory, honouring `:pdf`, `:page`, `:n` and `:autorotate`.
+  Processing, `get_image_sizes/1` and `get_image_format/1` then reuse the decoded image instead of reading the source again,
+  so one image can be encoded many times. The memory is released once the returned struct is garbage collected.
+
+  ## Examples
+      iex> import Elxvips
+      iex>
+      iex> { :ok, image } = from_file( "test/input.png" ) |> load()
+      iex> image |> resize( width: 300 ) |> webp() |> to_bytes()
+      {:ok, %ImageBytes{}}
+      iex> image |> resize( width: 600 ) |> jpg() |> to_bytes()
+      {:ok, %ImageBytes{}}
+  """
+  def load( image_file = %ImageFile{} ) do
+    with { :ok, handle } <- Elxvips.Native.vips_load_file( image_file ) do
+      { :ok, %ImageFile{ image_file | :handle => handle } }
+    end
+  end
+  def load( image_bytes = %ImageBytes{} ) do
+    with { :ok, handle } <- Elxvips.Native.vips_load_bytes( image_bytes ) do
+      { :ok, %ImageBytes{ image_bytes | :handle => handle } }
+    end
+  end
+  def load( { :ok, image } ), do: load( image )
+
   @doc """
   Returns format of the specified image, works with a image path or bytes.
 
@@ -482,6 +1602,7 @@ defmodule Elxvips do
       iex> |> get_image_format()
       {:ok, :png}
   """
+  def get_image_format( %{ :handle => handle } ) when is_reference( handle ), do: Elxvips.Native.vips_get_handle_format( handle )
   def get_image_format( %ImageFile{ :path => path } ), do: Elxvips.Native.vips_get_image_file_format( path )
   def get_image_format( {:ok, image_file = %ImageFile{} } ), do: get_image_format( image_file )
 
@@ -0,0 +1,26 @@
+defmodule Elxvips.Application do
+  @moduledoc false
+  use Application
+
+  def start( _type, _args ) do
+    case apply_block_config() do
+      :ok -> Supervisor.start_link( [], strategy: :one_for_one, name: Elxvips.Supervisor )
+      { :error, reason } -> { :error, { :block_options, reason } }
+    end
+  end
+
+  # the :block_options config is read when the application starts rather than when it is compiled,
+  # so precompiled NIFs pick up changes without a rebuild
+  def apply_block_config() do
+    case Application.get_env( :elxvips, :block_options, [] ) do
+      [] -> :ok
+      opts when is_list( opts ) ->
+        if Keyword.keyword?( opts ) do
+          Elxvips.set_block( opts )
+        else
+          { :error, "block_options must be a keyword list, got: #{ inspect( opts ) }" }
+        end
+      opts -> { :error, "block_options must be a keyword list, got: #{ inspect( opts ) }" }
+    end
+  end
+end
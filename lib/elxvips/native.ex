@@ -20,13 +20,40 @@ defmodule Elxvips.Native do
 
   # NIFs
   def set_concurrency(_a), do: :erlang.nif_error(:nif_not_loaded)
-  def vips_get_image_sizes(_a), do: :erlang.nif_error(:nif_not_loaded) # returns {:ok, { width, height } }
+  def set_cache(_a), do: :erlang.nif_error(:nif_not_loaded) # sets the limits of %CacheOptions{} that are not nil
+  def drop_cache(), do: :erlang.nif_error(:nif_not_loaded) # empties the operation cache
+  def get_memory_stats(), do: :erlang.nif_error(:nif_not_loaded) # returns {:ok, %MemoryStats{}}
+  def set_block(_a), do: :erlang.nif_error(:nif_not_loaded) # applies %BlockOptions{}, nothing is changed if an operation is not found
+  def get_allowed_loaders(), do: :erlang.nif_error(:nif_not_loaded) # returns {:ok, [ nickname ]} of the loaders not blocked
+  def vips_get_image_sizes(_a), do: :erlang.nif_error(:nif_not_loaded) # returns {:ok, { width, height } } after applying the orientation tag
   def vips_get_image_bytes_sizes(_a), do: :erlang.nif_error(:nif_not_loaded) # same but works with bytes
+  def vips_get_image_sizes_autorotate(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # same, the orientation tag only applied when the second argument is true
+  def vips_get_image_bytes_sizes_autorotate(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # same but works with bytes
   def vips_process_file_to_file(_a), do: :erlang.nif_error(:nif_not_loaded) # applies processing from %ImageFile{}
   def vips_process_file_to_bytes(_a), do: :erlang.nif_error(:nif_not_loaded) # applies processing from %ImageBytes{} created from image path
   def vips_process_bytes_to_bytes(_a), do: :erlang.nif_error(:nif_not_loaded) # applies processing from %ImageBytes{} created from image bytes
   def vips_process_bytes_to_file(_a), do: :erlang.nif_error(:nif_not_loaded) # applies processing from %ImageBytes{} created from image bytes
+  def vips_process_file_to_file_smartcrop(_a), do: :erlang.nif_error(:nif_not_loaded) # same as the four above, the smart crop made, or nil, added to the result
+  def vips_process_file_to_bytes_smartcrop(_a), do: :erlang.nif_error(:nif_not_loaded)
+  def vips_process_bytes_to_bytes_smartcrop(_a), do: :erlang.nif_error(:nif_not_loaded)
+  def vips_process_bytes_to_file_smartcrop(_a), do: :erlang.nif_error(:nif_not_loaded)
   def vips_get_image_file_format(_a), do: :erlang.nif_error(:nif_not_loaded) # applies processing from %ImageBytes{} created from image bytes
   def vips_get_image_bytes_format(_a), do: :erlang.nif_error(:nif_not_loaded) # applies processing from %ImageBytes{} created from image bytes
+  def vips_process_file_variants(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # encodes %ImageFile{} into every %Variant{}, decoding it once
+  def vips_process_bytes_variants(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # same but works with %ImageBytes{}
+  def vips_process_async(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # queues processing to :bytes or :file, returns {:ok, job}
+  def vips_cancel(_a), do: :erlang.nif_error(:nif_not_loaded) # cancels a queued or running job
+  def vips_process_batch(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # processes [ { image, :bytes | :file } ] on a pool of the given width, returns {:ok, batch}
+  def vips_cancel_batch(_a), do: :erlang.nif_error(:nif_not_loaded) # cancels the jobs of a batch not done yet, its results are not sent anymore
+  def vips_stream_process(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # streams the encoded image to the caller, optionally pulling the input from it, returns {:ok, stream}
+  def vips_stream_write(_a, _b), do: :erlang.nif_error(:nif_not_loaded) # answers {stream, :more} with the next input chunk
+  def vips_stream_close(_a), do: :erlang.nif_error(:nif_not_loaded) # answers {stream, :more} at the end of the input
+  def vips_stream_cancel(_a), do: :erlang.nif_error(:nif_not_loaded) # stops a running stream, nothing is sent to the caller afterwards
+  def vips_load_file(_a), do: :erlang.nif_error(:nif_not_loaded) # decodes %ImageFile{} into memory, returns {:ok, handle}
+  def vips_load_bytes(_a), do: :erlang.nif_error(:nif_not_loaded) # same but works with %ImageBytes{}
+  def vips_get_handle_sizes(_a), do: :erlang.nif_error(:nif_not_loaded) # returns {:ok, [ width, height ]} of a loaded image
+  def vips_get_handle_format(_a), do: :erlang.nif_error(:nif_not_loaded) # returns {:ok, format} of a loaded image
+  def vips_get_file_trim_box(_a), do: :erlang.nif_error(:nif_not_loaded) # returns {:ok, [ left, top, width, height ]} of the content of %ImageFile{}
+  def vips_get_bytes_trim_box(_a), do: :erlang.nif_error(:nif_not_loaded) # same but works with %ImageBytes{}
 
 end
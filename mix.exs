@@ -46,6 +46,7 @@ defmodule Elxvips.MixProject do
   # Run "mix help compile.app" to learn about applications.
   def application do
     [
+      mod: { Elxvips.Application, [] },
       extra_applications: [:logger]
     ]
   end
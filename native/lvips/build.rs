@@ -60,9 +60,23 @@ fn main() {
         .allowlist_function( "vips_error_buffer" )
         .allowlist_function( "vips_error_clear" )
         .allowlist_function( "vips_concurrency_set" )
+        .allowlist_function( "vips_cache_set_max" )
+        .allowlist_function( "vips_cache_set_max_mem" )
+        .allowlist_function( "vips_cache_set_max_files" )
+        .allowlist_function( "vips_cache_drop_all" )
+        .allowlist_function( "vips_cache_get_size" )
+        .allowlist_function( "vips_cache_get_max" )
+        .allowlist_function( "vips_cache_get_max_mem" )
+        .allowlist_function( "vips_cache_get_max_files" )
+        .allowlist_function( "vips_tracked_get_mem" )
+        .allowlist_function( "vips_tracked_get_mem_highwater" )
+        .allowlist_function( "vips_tracked_get_allocs" )
+        .allowlist_function( "vips_tracked_get_files" )
         .allowlist_function( "vips_image_new_from_file" )
         .allowlist_function( "vips_image_new_from_buffer" )
         .allowlist_function( "vips_image_get_width" )
+        .allowlist_function( "vips_image_get_xoffset" )
+        .allowlist_function( "vips_image_get_yoffset" )
         .allowlist_function( "vips_image_get_height" )
         .allowlist_function( "vips_jpegsave" )
         .allowlist_function( "vips_heifsave" )
@@ -75,15 +89,65 @@ fn main() {
         .allowlist_function( "vips_crop" )
         .allowlist_function( "vips_smartcrop" )
         .allowlist_function( "vips_array_double_new" )
+        .allowlist_function( "vips_area_unref" )
         .allowlist_function( "vips_resize" )
+        .allowlist_function( "vips_gravity" )
+        .allowlist_function( "vips_thumbnail" )
+        .allowlist_function( "vips_thumbnail_buffer" )
+        .allowlist_function( "vips_colourspace" )
+        .allowlist_function( "vips_image_guess_interpretation" )
+        .allowlist_function( "vips_autorot" )
+        .allowlist_function( "vips_image_get_orientation_swap" )
+        .allowlist_function( "vips_rot" )
+        .allowlist_function( "vips_rotate" )
+        .allowlist_function( "vips_flip" )
+        .allowlist_function( "vips_find_trim" )
+        .allowlist_function( "vips_getpoint" )
+        .allowlist_function( "vips_image_hasalpha" )
+        .allowlist_function( "vips_affine" )
+        .allowlist_function( "vips_mapim" )
+        .allowlist_function( "vips_xyz" )
+        .allowlist_function( "vips_bandjoin_const1" )
+        .allowlist_function( "vips_recomb" )
+        .allowlist_function( "vips_extract_band" )
+        .allowlist_function( "vips_divide" )
+        .allowlist_function( "vips_interpolate_new" )
+        .allowlist_function( "vips_image_new_matrix_from_array" )
+        .allowlist_function( "vips_gaussblur" )
+        .allowlist_function( "vips_composite2" )
+        .allowlist_function( "vips_image_get_bands" )
+        .allowlist_function( "vips_image_get_n_pages" )
         .allowlist_function( "vips_image_get_as_string" )
         .allowlist_function( "g_object_unref" )
+        .allowlist_function( "g_object_ref" )
+        .allowlist_function( "vips_image_copy_memory" )
+        .allowlist_function( "vips_image_set_kill" )
+        .allowlist_function( "vips_image_new" )
+        .allowlist_function( "vips_image_write" )
+        .allowlist_function( "vips_image_set_progress" )
+        .allowlist_function( "g_signal_handler_disconnect" )
         .allowlist_function( "g_free" )
         .allowlist_function( "vips_pdfload" )
         .allowlist_function( "vips_pdfload_buffer" )
         .allowlist_function( "vips_init" )
+        .allowlist_function( "g_signal_connect_data" )
+        .allowlist_function( "vips_source_custom_new" )
+        .allowlist_function( "vips_target_custom_new" )
+        .allowlist_function( "vips_image_new_from_source" )
+        .allowlist_function( "vips_jpegsave_target" )
+        .allowlist_function( "vips_pngsave_target" )
+        .allowlist_function( "vips_webpsave_target" )
+        .allowlist_function( "vips_heifsave_target" )
+        .allowlist_function( "vips_block_untrusted_set" )
+        .allowlist_function( "vips_operation_block_set" )
+        .allowlist_function( "vips_type_find" )
+        .allowlist_function( "vips_foreign_map" )
         .allowlist_var( "VipsInterpretation_VIPS_INTERPRETATION_XYZ" )
+        .allowlist_var( "VipsAccess_VIPS_ACCESS_SEQUENTIAL" )
+        .allowlist_var( "VipsOperationFlags_VIPS_OPERATION_BLOCKED" )
         .allowlist_type( "_VipsImage" )
+        .allowlist_type( "VipsProgress" )
+        .allowlist_type( "VipsOperationClass" )
         .generate()
         // Unwrap the Result and panic on failure.
         .expect("Unable to generate bindings");
@@ -1,10 +1,29 @@
 extern crate num_cpus;
 
-use rustler::{Encoder, Env, Error, Term, Atom, NifStruct, NifResult, Binary, OwnedBinary};
+use rustler::{Encoder, Env, Error, Term, Atom, NifStruct, NifTaggedEnum, NifResult, Binary, OwnedBinary, ResourceArc};
+use rustler::{LocalPid, Monitor, OwnedEnv};
+use rustler::env::SavedTerm;
 use std::env;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::collections::VecDeque;
+use std::sync::{mpsc, Arc, LazyLock, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 mod libvips;
+mod workers;
+use workers::WorkerPool;
 use libvips::{VipsImage, VipsFormat};
+use libvips::stream::{VipsSource, VipsTarget, ReadFn, WriteFn};
 use libvips::save_options::{JpegSaveOptions, PngSaveOptions, WebPSaveOptions, SmartcropOptions, Interesting, HeifsaveOptions};
+use libvips::save_options::{ResizeOptions as VipsResizeOptions, GravityOptions, CompassDirection, Extend, ThumbnailOptions, Size};
+use libvips::save_options::{Kernel, Interpretation, Angle, Direction};
+use libvips::save_options::RotateOptions as VipsRotateOptions;
+use libvips::save_options::FindTrimOptions;
+use libvips::save_options::{Interpolate, MapimOptions};
+use libvips::save_options::AffineOptions as VipsAffineOptions;
+use libvips::save_options::BlendMode;
+use libvips::save_options::{LoadOptions, FailOn};
 use rustler::types::atom::{ok, error};
 
 mod atoms {
@@ -16,15 +35,90 @@ mod atoms {
         webp,
         avif,
         svg,
+        cover,
+        contain,
+        fill,
+        inside,
+        outside,
+        centre,
+        center,
+        attention,
+        entropy,
+        low,
+        high,
+        all,
+        north,
+        north_east,
+        east,
+        south_east,
+        south,
+        south_west,
+        west,
+        north_west,
+        before,
+        after,
+        nearest,
+        linear,
+        cubic,
+        mitchell,
+        lanczos2,
+        lanczos3,
+        mks2013,
+        mks2021,
+        bilinear,
+        bicubic,
+        lbb,
+        nohalo,
+        vsqbs,
+        horizontal,
+        vertical,
+        over,
+        add,
+        saturate,
+        multiply,
+        screen,
+        overlay,
+        darken,
+        lighten,
+        colour_dodge,
+        colour_burn,
+        hard_light,
+        soft_light,
+        difference,
+        exclusion,
+        bytes,
+        file,
+        cancelled,
+        more,
+        chunk,
+        done,
+        timeout,
+        elxvips_progress,
+        truncated,
+        warning,
+        limit_exceeded,
     }
 }
 
-#[derive(NifStruct, Debug)]
+// decoded image held by Elixir, freed by VipsImage's Drop once the term is garbage collected
+#[rustler::resource_impl]
+impl rustler::Resource for VipsImage {}
+
+#[derive(NifStruct, Clone, Debug)]
 #[module = "Elxvips.ResizeOptions"]
 struct ResizeOptions {
     pub width: i32,
     pub height: i32,
     pub resize_type: Atom,
+    pub gravity: Atom,
+    pub kernel: Atom,
+    pub linear: bool,
+    pub without_enlargement: bool,
+    pub max_width: i32,
+    pub max_height: i32,
+    pub longest_edge: i32,
+    pub scale: f64,
+    pub focal_point: Option<( f64, f64 )>,
 }
 
 #[derive(NifStruct, Debug)]
@@ -38,15 +132,190 @@ struct SaveOptions {
     background: Vec<f64>,
 }
 
+#[derive(NifStruct, Clone, Debug)]
+#[module = "Elxvips.CropOptions"]
+struct CropOptions {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+    pub stage: Atom,
+}
+
+#[derive(NifStruct, Clone, Debug)]
+#[module = "Elxvips.RotateOptions"]
+struct RotateOptions {
+    pub angle: f64,
+    pub background: Vec<f64>,
+}
+
+#[derive(NifStruct, Clone, Debug)]
+#[module = "Elxvips.EmbedOptions"]
+struct EmbedOptions {
+    pub width: i32,
+    pub height: i32,
+    pub gravity: Atom,
+    pub background: Vec<f64>,
+}
+
+#[derive(NifStruct, Clone, Debug)]
+#[module = "Elxvips.AffineOptions"]
+struct AffineOptions {
+    pub matrix: Vec<f64>,
+    pub odx: f64,
+    pub ody: f64,
+    pub idx: f64,
+    pub idy: f64,
+    pub interpolate: Atom,
+    pub background: Vec<f64>,
+}
+
+#[derive(NifStruct, Clone, Debug)]
+#[module = "Elxvips.PerspectiveOptions"]
+struct PerspectiveOptions {
+    pub corners: Vec<( f64, f64 )>,
+    pub width: i32,
+    pub height: i32,
+    pub interpolate: Atom,
+    pub background: Vec<f64>,
+}
+
+#[derive(NifStruct, Clone, Debug)]
+#[module = "Elxvips.BlurOptions"]
+struct BlurOptions {
+    pub sigma: f64,
+}
+
+// overlay is loaded from path or bytes, placed by gravity then moved by left and top
+#[derive(NifStruct, Clone)]
+#[module = "Elxvips.CompositeOptions"]
+struct CompositeOptions<'a> {
+    pub path: Option<String>,
+    pub bytes: Option<Binary<'a>>,
+    pub left: i32,
+    pub top: i32,
+    pub gravity: Atom,
+    pub blend: Atom,
+}
+
+// one step of the operations list, decoded from {:resize, %Elxvips.ResizeOptions{}} and alike
+#[derive(NifTaggedEnum, Clone)]
+enum Operation<'a> {
+    Resize( ResizeOptions ),
+    Crop( CropOptions ),
+    Trim( TrimOptions ),
+    Rotate( RotateOptions ),
+    Flip( Atom ),
+    Embed( EmbedOptions ),
+    Affine( AffineOptions ),
+    Perspective( PerspectiveOptions ),
+    Blur( BlurOptions ),
+    Composite( CompositeOptions<'a> ),
+}
+
+// one output of a fan-out, resized and saved on its own from the shared image
+#[derive(NifStruct, Debug)]
+#[module = "Elxvips.Variant"]
+struct Variant {
+    pub resize: ResizeOptions,
+    pub save: SaveOptions,
+}
+
+#[derive(NifStruct, Debug)]
+#[module = "Elxvips.CacheOptions"]
+struct CacheOptions {
+    pub max: Option<i64>,
+    pub max_mem: Option<i64>,
+    pub max_files: Option<i64>,
+}
+
+// libvips memory and operation cache, sizes in bytes
 #[derive(NifStruct, Debug)]
+#[module = "Elxvips.MemoryStats"]
+struct MemoryStats {
+    pub mem: u64,
+    pub mem_highwater: u64,
+    pub allocs: i32,
+    pub files: i32,
+    pub cache_size: i32,
+    pub cache_max: i32,
+    pub cache_max_mem: u64,
+    pub cache_max_files: i32,
+}
+
+// where the smart crop landed, relative to the resized image it was taken from
+#[derive(NifStruct, Debug)]
+#[module = "Elxvips.Smartcrop"]
+struct Smartcrop {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+    pub attention_x: i32,
+    pub attention_y: i32,
+    pub image_width: i32,
+    pub image_height: i32,
+}
+
+#[derive(NifStruct, Clone, Debug)]
+#[module = "Elxvips.TrimOptions"]
+struct TrimOptions {
+    pub threshold: f64,
+    pub background: Vec<f64>,
+}
+
+// checked against the header before any pixel is decoded, nil leaves a limit unchecked
+#[derive(NifStruct, Debug)]
+#[module = "Elxvips.LoadLimits"]
+struct LoadLimits {
+    pub max_pixels: Option<u64>,
+    pub max_width: Option<u64>,
+    pub max_height: Option<u64>,
+    pub max_pages: Option<u64>,
+    pub max_bytes: Option<u64>,
+    pub fail_on: Atom,
+}
+
+// applied in order: untrusted operations, then block, then allow, so allow can reopen part of a blocked class
+#[derive(NifStruct)]
+#[module = "Elxvips.BlockOptions"]
+struct BlockOptions {
+    pub block_untrusted: Option<bool>,
+    pub block: Vec<String>,
+    pub allow: Vec<String>,
+}
+
+// progress messages {:elxvips_progress, tag, percent} are sent to pid while the image is encoded
+#[derive(NifStruct)]
+#[module = "Elxvips.ProgressOptions"]
+struct ProgressOptions<'a> {
+    pub pid: LocalPid,
+    pub tag: Term<'a>,
+}
+
+#[derive(NifStruct)]
 #[module = "Elxvips.ImageFile"]
-struct ImageFile {
+struct ImageFile<'a> {
     pub path: String,
     pub resize: ResizeOptions,
     pub save: SaveOptions,
     pub pdf: bool,
     pub page: i32,
     pub n: i32,
+    pub crop: Option<CropOptions>,
+    pub autorotate: bool,
+    pub rotate: Option<RotateOptions>,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub embed: Option<EmbedOptions>,
+    pub trim: Option<TrimOptions>,
+    pub affine: Option<AffineOptions>,
+    pub perspective: Option<PerspectiveOptions>,
+    pub operations: Vec<Operation<'a>>,
+    pub handle: Option<ResourceArc<VipsImage>>,
+    pub timeout_ms: Option<u64>,
+    pub progress: Option<ProgressOptions<'a>>,
+    pub limits: Option<LoadLimits>,
 }
 
 #[derive(NifStruct)]
@@ -58,11 +327,190 @@ struct ImageBytes<'a> {
     pub pdf: bool,
     pub page: i32,
     pub n: i32,
+    pub crop: Option<CropOptions>,
+    pub autorotate: bool,
+    pub rotate: Option<RotateOptions>,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub embed: Option<EmbedOptions>,
+    pub trim: Option<TrimOptions>,
+    pub affine: Option<AffineOptions>,
+    pub perspective: Option<PerspectiveOptions>,
+    pub operations: Vec<Operation<'a>>,
+    pub handle: Option<ResourceArc<VipsImage>>,
+    pub timeout_ms: Option<u64>,
+    pub progress: Option<ProgressOptions<'a>>,
+    pub limits: Option<LoadLimits>,
+}
+
+// processing options shared by ImageFile and ImageBytes
+struct Pipeline<'a, 'b> {
+    resize: &'a ResizeOptions,
+    save: &'a SaveOptions,
+    crop: &'a Option<CropOptions>,
+    rotate: &'a Option<RotateOptions>,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    embed: &'a Option<EmbedOptions>,
+    trim: &'a Option<TrimOptions>,
+    affine: &'a Option<AffineOptions>,
+    perspective: &'a Option<PerspectiveOptions>,
+    operations: &'a [Operation<'b>],
+    // counts from when the pipeline is taken, before the image is loaded
+    deadline: Option<Instant>,
+    progress: &'a Option<ProgressOptions<'b>>,
+    limits: &'a Option<LoadLimits>,
+    // the async job the pipeline runs for, cancelling it kills the images it computes from
+    job: Option<&'a Job>,
+}
+
+fn deadline_after( timeout_ms: Option<u64> ) -> Option<Instant> {
+    timeout_ms.map( |timeout_ms| Instant::now() + Duration::from_millis( timeout_ms ) )
+}
+
+impl<'a> ImageFile<'a> {
+    fn pipeline( &self ) -> Pipeline<'_, 'a> {
+        Pipeline {
+            resize: &self.resize,
+            save: &self.save,
+            crop: &self.crop,
+            rotate: &self.rotate,
+            flip_horizontal: self.flip_horizontal,
+            flip_vertical: self.flip_vertical,
+            embed: &self.embed,
+            trim: &self.trim,
+            affine: &self.affine,
+            perspective: &self.perspective,
+            operations: &self.operations,
+            deadline: deadline_after( self.timeout_ms ),
+            progress: &self.progress,
+            limits: &self.limits,
+            job: None,
+        }
+    }
+}
+
+impl<'a> ImageBytes<'a> {
+    fn pipeline( &self ) -> Pipeline<'_, 'a> {
+        Pipeline {
+            resize: &self.resize,
+            save: &self.save,
+            crop: &self.crop,
+            rotate: &self.rotate,
+            flip_horizontal: self.flip_horizontal,
+            flip_vertical: self.flip_vertical,
+            embed: &self.embed,
+            trim: &self.trim,
+            affine: &self.affine,
+            perspective: &self.perspective,
+            operations: &self.operations,
+            deadline: deadline_after( self.timeout_ms ),
+            progress: &self.progress,
+            limits: &self.limits,
+            job: None,
+        }
+    }
+}
+
+enum CropGravity {
+    Smart( Interesting ),
+    Compass( CompassDirection ),
+    Focal( f64, f64 ),
+}
+
+fn atom_to_gravity( gravity: Atom ) -> Result<CropGravity, String> {
+    match gravity {
+        gravity if gravity == atoms::centre() || gravity == atoms::center() => Ok( CropGravity::Smart( Interesting::Centre ) ),
+        gravity if gravity == atoms::attention() => Ok( CropGravity::Smart( Interesting::Attention ) ),
+        gravity if gravity == atoms::entropy() => Ok( CropGravity::Smart( Interesting::Entropy ) ),
+        gravity if gravity == atoms::low() => Ok( CropGravity::Smart( Interesting::Low ) ),
+        gravity if gravity == atoms::high() => Ok( CropGravity::Smart( Interesting::High ) ),
+        gravity if gravity == atoms::all() => Ok( CropGravity::Smart( Interesting::All ) ),
+        gravity => match atom_to_direction( gravity ) {
+            Ok( direction ) => Ok( CropGravity::Compass( direction ) ),
+            Err( err ) => Err( err )
+        }
+    }
+}
+
+fn atom_to_direction( gravity: Atom ) -> Result<CompassDirection, String> {
+    match gravity {
+        gravity if gravity == atoms::centre() || gravity == atoms::center() => Ok( CompassDirection::Centre ),
+        gravity if gravity == atoms::north() => Ok( CompassDirection::North ),
+        gravity if gravity == atoms::north_east() => Ok( CompassDirection::NorthEast ),
+        gravity if gravity == atoms::east() => Ok( CompassDirection::East ),
+        gravity if gravity == atoms::south_east() => Ok( CompassDirection::SouthEast ),
+        gravity if gravity == atoms::south() => Ok( CompassDirection::South ),
+        gravity if gravity == atoms::south_west() => Ok( CompassDirection::SouthWest ),
+        gravity if gravity == atoms::west() => Ok( CompassDirection::West ),
+        gravity if gravity == atoms::north_west() => Ok( CompassDirection::NorthWest ),
+        _ => Err( "gravity not supported".to_string() )
+    }
 }
 
-static SMART_CROP_OPTS: SmartcropOptions = SmartcropOptions {
-    interesting: Interesting::Centre,
-};
+// offset of a width x height window centred on a relative point, kept inside the image
+fn focal_offset( x: f64, y: f64, image_width: i32, image_height: i32, width: i32, height: i32 ) -> ( i32, i32 ) {
+    let left = ( x * image_width as f64 - width as f64 / 2.0 ).round() as i32;
+    let top = ( y * image_height as f64 - height as f64 / 2.0 ).round() as i32;
+    ( left.max( 0 ).min( image_width - width ), top.max( 0 ).min( image_height - height ) )
+}
+
+// offset of a width x height window anchored to the given side of the image
+fn gravity_offset( direction: CompassDirection, image_width: i32, image_height: i32, width: i32, height: i32 ) -> ( i32, i32 ) {
+    let left = match direction {
+        CompassDirection::West | CompassDirection::NorthWest | CompassDirection::SouthWest => 0,
+        CompassDirection::East | CompassDirection::NorthEast | CompassDirection::SouthEast => image_width - width,
+        _ => ( image_width - width ) / 2,
+    };
+    let top = match direction {
+        CompassDirection::North | CompassDirection::NorthWest | CompassDirection::NorthEast => 0,
+        CompassDirection::South | CompassDirection::SouthWest | CompassDirection::SouthEast => image_height - height,
+        _ => ( image_height - height ) / 2,
+    };
+    ( left, top )
+}
+
+fn crop_to_gravity( image: VipsImage, width: i32, height: i32, gravity: &CropGravity, guard: &Guard ) -> Result<( VipsImage, Option<Smartcrop> ), String> {
+    let result = match gravity {
+        CropGravity::Smart( interesting ) => {
+            let options = SmartcropOptions {
+                interesting: *interesting,
+            };
+            // finding the interesting area computes the image
+            match guard.run( &image, |image| image.smart_crop_opts( width, height, &options ) ) {
+                Ok( ( cropped, crop ) ) => return Ok( ( cropped, Some( Smartcrop {
+                    left: crop.left,
+                    top: crop.top,
+                    width: crop.width,
+                    height: crop.height,
+                    attention_x: crop.attention_x,
+                    attention_y: crop.attention_y,
+                    image_width: image.get_width(),
+                    image_height: image.get_height(),
+                } ) ) ),
+                Err( err ) => Err( err )
+            }
+        },
+        CropGravity::Compass( direction ) => {
+            // never crop outside the image, rounding may leave the resized image a pixel short
+            let width = width.min( image.get_width() );
+            let height = height.min( image.get_height() );
+            let ( left, top ) = gravity_offset( *direction, image.get_width(), image.get_height(), width, height );
+            image.crop( left, top, width, height )
+        },
+        CropGravity::Focal( x, y ) => {
+            let width = width.min( image.get_width() );
+            let height = height.min( image.get_height() );
+            let ( left, top ) = focal_offset( *x, *y, image.get_width(), image.get_height(), width, height );
+            image.crop( left, top, width, height )
+        }
+    };
+
+    match result {
+        Ok( cropped ) => Ok( ( cropped, None ) ),
+        Err( err ) => Err( format!( "failed to crop image: {}", err ) )
+    }
+}
 
 fn format_to_atom( format: VipsFormat ) -> Atom {
     match format {
@@ -74,8 +522,16 @@ fn format_to_atom( format: VipsFormat ) -> Atom {
     }
 }
 
-fn image_into_bytes<'a>(image: VipsImage, save_options: &SaveOptions) -> Result<Vec<u8>, String> {
+// the saver the options ask for, with the settings every output is encoded with
+enum Saver {
+    Jpeg( JpegSaveOptions ),
+    Png( PngSaveOptions ),
+    WebP( WebPSaveOptions ),
+    Avif( HeifsaveOptions ),
+    Svg,
+}
 
+fn saver( image: &VipsImage, save_options: &SaveOptions ) -> Result<Saver, String> {
     let vips_format = match save_options.format {
         format if format == atoms::jpg() => VipsFormat::JPEG,
         format if format == atoms::png() => VipsFormat::PNG,
@@ -88,82 +544,53 @@ fn image_into_bytes<'a>(image: VipsImage, save_options: &SaveOptions) -> Result<
         }
     };
 
-    match vips_format {
-        VipsFormat::JPEG => {
-            let options = JpegSaveOptions {
-                q: save_options.quality as i32,
-                strip: save_options.strip,
-                optimize_coding: true,
-                optimize_scans: true,
-                interlace: true,
-                background: save_options.background.to_owned(),
-                ..JpegSaveOptions::default()
-            };
-
-            match image.jpeg_buffer_opts(&options) {
-                Ok ( bytes ) => Ok( bytes ),
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
-
-        },
-        VipsFormat::PNG => {
-            let options = PngSaveOptions {
-                q: save_options.quality as i32,
-                strip: save_options.strip,
-                compression: save_options.compression as i32,
-                interlace: true,
-                background: save_options.background.to_owned(),
-                ..PngSaveOptions::default()
-            };
-
-            match image.png_buffer_opts(&options){
-                Ok ( bytes ) => {
-                    Ok( bytes )
-                }
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
-
-        },
-        VipsFormat::WEBP => {
-            let options = WebPSaveOptions {
-                q: save_options.quality as i32,
-                strip: save_options.strip,
-                background: save_options.background.to_owned(),
-                ..WebPSaveOptions::default()
-            };
-
-            match image.webp_buffer_opts(&options) {
-                Ok ( bytes ) => {
-                    Ok( bytes )
-                }
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
-
-        },
-        VipsFormat::AVIF => {
-            let options = HeifsaveOptions {
-                q: save_options.quality as i32,
-                background: save_options.background.to_owned(),
-                compression: libvips::save_options::ForeignHeifCompression::Av1,
-                ..HeifsaveOptions::default()
-            };
+    let saver = match vips_format {
+        VipsFormat::JPEG => Saver::Jpeg( JpegSaveOptions {
+            q: save_options.quality as i32,
+            strip: save_options.strip,
+            optimize_coding: true,
+            optimize_scans: true,
+            interlace: true,
+            background: save_options.background.to_owned(),
+            ..JpegSaveOptions::default()
+        } ),
+        VipsFormat::PNG => Saver::Png( PngSaveOptions {
+            q: save_options.quality as i32,
+            strip: save_options.strip,
+            compression: save_options.compression as i32,
+            interlace: true,
+            background: save_options.background.to_owned(),
+            ..PngSaveOptions::default()
+        } ),
+        VipsFormat::WEBP => Saver::WebP( WebPSaveOptions {
+            q: save_options.quality as i32,
+            strip: save_options.strip,
+            background: save_options.background.to_owned(),
+            ..WebPSaveOptions::default()
+        } ),
+        VipsFormat::AVIF => Saver::Avif( HeifsaveOptions {
+            q: save_options.quality as i32,
+            background: save_options.background.to_owned(),
+            compression: libvips::save_options::ForeignHeifCompression::Av1,
+            ..HeifsaveOptions::default()
+        } ),
+        VipsFormat::SVG => Saver::Svg,
+    };
+    Ok( saver )
+}
 
-            match image.avif_buffer_opts(&options) {
-                Ok ( bytes ) => {
-                    Ok( bytes )
-                }
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
+fn image_into_bytes(image: &VipsImage, save_options: &SaveOptions) -> Result<OwnedBinary, String> {
+    let result = match saver( image, save_options )? {
+        Saver::Jpeg( options ) => image.jpeg_buffer_opts( &options ).and_then( |bytes| into_binary( &bytes ) ),
+        Saver::Png( options ) => image.png_buffer_opts( &options ).and_then( |bytes| into_binary( &bytes ) ),
+        Saver::WebP( options ) => image.webp_buffer_opts( &options ).and_then( |bytes| into_binary( &bytes ) ),
+        Saver::Avif( options ) => image.avif_buffer_opts( &options ).and_then( |bytes| into_binary( &bytes ) ),
+        Saver::Svg => image.svg_buffer().and_then( |bytes| into_binary( &bytes ) ),
+    };
 
-        },
-        VipsFormat::SVG => {
-            match image.svg_buffer() {
-                Ok ( bytes ) => {
-                    Ok( bytes )
-                }
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
-        }
+    match result {
+        Ok( bytes ) => Ok( bytes ),
+        Err( err ) => Err( format!( "failed to save image: {}", err ) )
     }
 }
 
@@ -174,10 +601,28 @@ fn image_from_bytes(buffer: &[u8]) -> Result<VipsImage, String> {
     }
 }
 
+// dimensions as they are stored, or as displayed once the orientation tag is applied
+fn image_sizes( image: &VipsImage, autorotate: bool ) -> [i32; 2] {
+    match autorotate && image.orientation_swap() {
+        true => [ image.get_height(), image.get_width() ],
+        false => [ image.get_width(), image.get_height() ],
+    }
+}
+
+// sizes as displayed, the orientation tag applied like processing does by default
 #[rustler::nif]
 fn vips_get_image_bytes_sizes<'a>(env: Env<'a>, bytes: Binary<'a>) -> Result<Term<'a>, Error> {
+    get_image_bytes_sizes( env, bytes, true )
+}
+
+#[rustler::nif]
+fn vips_get_image_bytes_sizes_autorotate<'a>(env: Env<'a>, bytes: Binary<'a>, autorotate: bool) -> Result<Term<'a>, Error> {
+    get_image_bytes_sizes( env, bytes, autorotate )
+}
+
+fn get_image_bytes_sizes<'a>(env: Env<'a>, bytes: Binary<'a>, autorotate: bool) -> Result<Term<'a>, Error> {
     let result = match image_from_bytes( &bytes ) {
-        Ok( image ) => Ok( [ image.get_width(), image.get_height() ] ),
+        Ok( image ) => Ok( image_sizes( &image, autorotate ) ),
         Err( err ) => Err( format!( "failed to read image from bytes: {}", err ) )
     };
 
@@ -216,13 +661,33 @@ fn vips_get_image_bytes_format<'a>(env: Env<'a>, bytes: Binary<'a>) -> Result<Te
     }
 }
 
+// nothing is blocked or unblocked unless every operation name is known
+fn apply_block( options: &BlockOptions ) -> Result<(), String> {
+    if let Some( name ) = options.block.iter().chain( options.allow.iter() ).find( |name| !libvips::operation_exists( name ) ) {
+        return Err( format!( "operation {} not found", name ) );
+    }
+
+    if let Some( block_untrusted ) = options.block_untrusted {
+        libvips::block_untrusted_set( block_untrusted );
+    }
+    for name in &options.block {
+        libvips::operation_block_set( name, true )?;
+    }
+    for name in &options.allow {
+        libvips::operation_block_set( name, false )?;
+    }
+    Ok( () )
+}
+
+// a panic here would take the VM down, failing the load makes :erlang.load_nif return an error instead
 fn on_load(_env: Env, _info: Term) -> bool {
     libvips::vips_init();
     let concurrency = match env::var( "VIPS_CONCURRENCY" ) {
         Ok( var ) => match var.parse::<u8>() {
             Ok( num ) => num,
             Err( _ ) => {
-                panic!( "Couldn't convert VIPS_CONCURRENCY={:?} to int", var )
+                eprintln!( "Couldn't convert VIPS_CONCURRENCY={:?} to int", var );
+                return false;
             }
         },
         Err(_) => num_cpus::get() as u8
@@ -231,10 +696,20 @@ fn on_load(_env: Env, _info: Term) -> bool {
     true
 }
 
+// sizes as displayed, the orientation tag applied like processing does by default
 #[rustler::nif]
 fn vips_get_image_sizes<'a>(env: Env<'a>, image_path: &str ) -> Result<Term<'a>, Error> {
+    get_image_sizes( env, image_path, true )
+}
+
+#[rustler::nif]
+fn vips_get_image_sizes_autorotate<'a>(env: Env<'a>, image_path: &str, autorotate: bool ) -> Result<Term<'a>, Error> {
+    get_image_sizes( env, image_path, autorotate )
+}
+
+fn get_image_sizes<'a>(env: Env<'a>, image_path: &str, autorotate: bool ) -> Result<Term<'a>, Error> {
     let result = match VipsImage::from_file( &image_path ) {
-        Ok( image ) => Ok( [ image.get_width(), image.get_height() ] ),
+        Ok( image ) => Ok( image_sizes( &image, autorotate ) ),
         Err( err ) => Err( format!( "failed to open image: {}", err ) )
     };
 
@@ -250,229 +725,1928 @@ fn set_concurrency<'a>(env: Env<'a>, concurrency: u8) -> Result<Term<'a>, Error>
     Ok( ( ok() ).encode( env ) )
 }
 
-fn resize_image(image: VipsImage, resize: &ResizeOptions) -> Result<VipsImage, String> {
-    let source_width = image.get_width();
-    let source_height = image.get_height();
+// libvips takes ints for the counts and a size_t for the memory, nothing is set unless every limit fits
+fn cache_limit( name: &str, value: Option<i64>, max: i64 ) -> Result<Option<i64>, String> {
+    match value {
+        Some( value ) if value < 0 || value > max => Err( format!( "cache {} must be between 0 and {}, got {}", name, max, value ) ),
+        value => Ok( value )
+    }
+}
 
-    let target_width = resize.width;
-    let target_height = resize.height;
+fn apply_cache( options: &CacheOptions ) -> Result<(), String> {
+    let max = cache_limit( "max", options.max, i32::MAX as i64 )?;
+    let max_mem = cache_limit( "max_mem", options.max_mem, isize::MAX as i64 )?;
+    let max_files = cache_limit( "max_files", options.max_files, i32::MAX as i64 )?;
 
-    let original_size = ( target_width == 0 && target_height == 0 ) ||
-        ( target_width == source_width && target_height == source_height ) ||
-        ( target_width == source_width && target_height == 0 ) ||
-        ( target_height == source_height && source_width == 0 );
+    if let Some( max ) = max {
+        libvips::cache_set_max( max as i32 );
+    }
+    if let Some( max_mem ) = max_mem {
+        libvips::cache_set_max_mem( max_mem as usize );
+    }
+    if let Some( max_files ) = max_files {
+        libvips::cache_set_max_files( max_files as i32 );
+    }
+    Ok( () )
+}
 
-    if original_size {
-        Ok( image )
-    } else {
-        let source_ratio = source_width as f64 / source_height as f64;
+// limits left as nil keep their current value
+#[rustler::nif]
+fn set_cache<'a>(env: Env<'a>, options: CacheOptions) -> Result<Term<'a>, Error> {
+    match apply_cache( &options ) {
+        Ok( () ) => Ok( ( ok() ).encode( env ) ),
+        Err( err ) => Ok( ( error(), err ).encode( env ) )
+    }
+}
 
-        let target_width_f64 = ( target_height as f64 * source_width as f64 / source_height as f64 ) * ( target_width == 0 ) as i32 as f64 +
-            target_width as f64 * ( target_width != 0 ) as i32 as f64;
-        let target_height_f64 = ( target_width as f64 * source_height as f64 / source_width as f64 ) * ( target_height == 0 ) as i32 as f64 +
-            target_height as f64 * ( target_height != 0 ) as i32 as f64;
+#[rustler::nif]
+fn drop_cache<'a>(env: Env<'a>) -> Result<Term<'a>, Error> {
+    libvips::cache_drop_all();
+    Ok( ( ok() ).encode( env ) )
+}
 
-        let target_ratio = target_width_f64 / target_height_f64;
-        
-        let resize_width = 
-                source_width as f64 * target_height_f64 / source_height as f64  * ( source_ratio >= target_ratio ) as i32 as f64 +
-                target_width_f64 * ( source_ratio < target_ratio ) as i32 as f64;
+#[rustler::nif]
+fn get_memory_stats<'a>(env: Env<'a>) -> Result<Term<'a>, Error> {
+    let tracked = libvips::tracked_stats();
+    let cache = libvips::cache_stats();
 
-        let scale = resize_width.ceil() / source_width as f64;
+    let stats = MemoryStats {
+        mem: tracked.mem as u64,
+        mem_highwater: tracked.mem_highwater as u64,
+        allocs: tracked.allocs,
+        files: tracked.files,
+        cache_size: cache.size,
+        cache_max: cache.max,
+        cache_max_mem: cache.max_mem as u64,
+        cache_max_files: cache.max_files,
+    };
+    Ok( ( ok(), stats ).encode( env ) )
+}
 
-        match image.resize( scale ) {
-            Ok( resized ) => {
+#[rustler::nif]
+fn set_block<'a>(env: Env<'a>, options: BlockOptions) -> Result<Term<'a>, Error> {
+    match apply_block( &options ) {
+        Ok( () ) => Ok( ( ok() ).encode( env ) ),
+        Err( err ) => Ok( ( error(), err ).encode( env ) )
+    }
+}
 
-                match resized.smart_crop_opts(target_width_f64 as i32, target_height_f64 as i32, &SMART_CROP_OPTS) {
-                    Ok( cropped ) => Ok( cropped ),
-                    Err( err ) => Err( format!( "failed to crop image: {}", err ) )
-                }
-            },
-            Err( err ) => Err( format!( "failed to resize image: {}", err ) )
-        }
+// loaders a file or buffer can still be opened with, by nickname
+#[rustler::nif]
+fn get_allowed_loaders<'a>(env: Env<'a>) -> Result<Term<'a>, Error> {
+    Ok( ( ok(), libvips::allowed_loaders() ).encode( env ) )
+}
 
+fn background_for( image: &VipsImage, background: &[f64] ) -> Vec<f64> {
+    // match the background to an image with an alpha channel, making the padding opaque
+    let mut background = background.to_vec();
+    if background.len() as i32 + 1 == image.get_bands() {
+        background.push( 255.0 );
     }
+    background
+}
 
+fn gravity_options( image: &VipsImage, background: &[f64] ) -> GravityOptions {
+    match background.len() {
+        0 => GravityOptions::default(),
+        _ => GravityOptions {
+            extend: Extend::Background,
+            background: background_for( image, background ),
+        }
+    }
 }
 
-fn save_image( image: &VipsImage, save_options: &SaveOptions ) -> Result<(), String> {
+fn atom_to_kernel( kernel: Atom ) -> Result<Kernel, String> {
+    match kernel {
+        kernel if kernel == atoms::nearest() => Ok( Kernel::Nearest ),
+        kernel if kernel == atoms::linear() => Ok( Kernel::Linear ),
+        kernel if kernel == atoms::cubic() => Ok( Kernel::Cubic ),
+        kernel if kernel == atoms::mitchell() => Ok( Kernel::Mitchell ),
+        kernel if kernel == atoms::lanczos2() => Ok( Kernel::Lanczos2 ),
+        kernel if kernel == atoms::lanczos3() => Ok( Kernel::Lanczos3 ),
+        kernel if kernel == atoms::mks2013() => Ok( Kernel::Mks2013 ),
+        kernel if kernel == atoms::mks2021() => Ok( Kernel::Mks2021 ),
+        _ => Err( "kernel not supported".to_string() )
+    }
+}
 
-    let vips_format = match save_options.format {
-        format if format == atoms::jpg() => VipsFormat::JPEG,
-        format if format == atoms::png() => VipsFormat::PNG,
-        format if format == atoms::webp() => VipsFormat::WEBP,
-        format if format == atoms::avif() => VipsFormat::AVIF,
-        format if format == atoms::svg() => VipsFormat::SVG,
-        format if format == atoms::auto() => image.get_format().unwrap(),
-        _ => {
-            return Err( "format not supported".to_string() )
-        }
+fn resample_image( image: VipsImage, hscale: f64, vscale: f64, kernel: Kernel ) -> Result<VipsImage, String> {
+    let options = VipsResizeOptions {
+        kernel: kernel,
+        vscale: vscale,
     };
+    image.resize_opts( hscale, &options )
+}
 
-    match vips_format {
-        VipsFormat::JPEG => {
-            let options = JpegSaveOptions {
-                q: save_options.quality as i32,
-                strip: save_options.strip,
-                optimize_coding: true,
-                optimize_scans: true,
-                interlace: true,
-                background: save_options.background.to_owned(),
-                ..JpegSaveOptions::default()
-            };
-
-            match image.save_jpeg_opts(&save_options.path, &options) {
-                Ok ( () ) => Ok( () ),
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
+// vips_thumbnail reads the source again, which has to pass the checks of the first load,
+// a file may have changed in between, returns the options the source was loaded with for the reload
+fn check_reload( image: &VipsImage, guard: &Guard ) -> Result<LoadOptions, String> {
+    let result = load_options( guard.limits ).and_then( |options| {
+        if guard.limits.is_none() {
+            return Ok( options );
+        }
+        if let Some( size ) = image.source_size() {
+            check_input_bytes( guard.limits, size )?;
+        }
+        match image.reopen( &options ) {
+            Ok( reopened ) => check_image_limits( &reopened, guard.limits ).map( |_| options ),
+            Err( err ) => Err( LoadError::Failed( err ) )
+        }
+    } );
 
-        },
-        VipsFormat::PNG => {
-            let options = PngSaveOptions {
-                q: save_options.quality as i32,
-                compression: save_options.compression as i32,
-                strip: save_options.strip,
-                interlace: true,
-                background: save_options.background.to_owned(),
-                ..PngSaveOptions::default()
-            };
+    match result {
+        Ok( options ) => Ok( options ),
+        Err( err ) => Err( format!( "failed to reload image: {}", err.message() ) )
+    }
+}
 
-            match image.save_png_opts(&save_options.path, &options) {
-                Ok ( () ) => Ok( () ),
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
+fn scale_image( image: VipsImage, hscale: f64, vscale: f64, resize: &ResizeOptions, guard: &Guard ) -> Result<VipsImage, String> {
+    let kernel = match atom_to_kernel( resize.kernel ) {
+        Ok( kernel ) => kernel,
+        Err( err ) => return Err( err )
+    };
+    let downscale = hscale <= 1.0 && vscale <= 1.0 && ( hscale < 1.0 || vscale < 1.0 );
+    // vips_thumbnail always reduces with lanczos3
+    let thumbnail = image.is_pristine() && downscale && matches!( kernel, Kernel::Lanczos3 );
 
-        },
-        VipsFormat::WEBP => {
-            let options = WebPSaveOptions {
-                q: save_options.quality as i32,
-                strip: save_options.strip,
-                background: save_options.background.to_owned(),
-                ..WebPSaveOptions::default()
+    let result = match ( thumbnail, resize.linear ) {
+        // reload straight from the source so the loader can shrink while decoding,
+        // forcing the exact size vips_resize would have produced
+        ( true, linear ) => {
+            let load = match check_reload( &image, guard ) {
+                Ok( load ) => load,
+                Err( err ) => return Err( err )
             };
-
-            match image.save_webp_opts(&save_options.path, &options) {
-                Ok ( () ) => Ok( () ),
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
-
-        },
-        VipsFormat::AVIF => {
-            let options = HeifsaveOptions {
-                q: save_options.quality as i32,
-                background: save_options.background.to_owned(),
-                compression: libvips::save_options::ForeignHeifCompression::Av1,
-                ..HeifsaveOptions::default()
+            let options = ThumbnailOptions {
+                height: ( ( image.get_height() as f64 * vscale ).round() as i32 ).max( 1 ),
+                size: Size::Force,
+                no_rotate: !image.is_autorotated(),
+                linear: linear,
+                fail_on: load.fail_on,
             };
-
-            match image.save_heif_opts(&save_options.path, &options) {
-                Ok ( () ) => Ok( () ),
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
+            // the reload doesn't compute from the tracked image, it needs tracking of its own
+            image.thumbnail( ( ( image.get_width() as f64 * hscale ).round() as i32 ).max( 1 ), &options )
+                .and_then( |thumbnail| guard.track( thumbnail ) )
         },
-        VipsFormat::SVG => {
-            match image.save_svg(&save_options.path) {
-                Ok ( () ) => Ok( () ),
-                Err( err )  => Err( format!( "failed to save image: {}", err ) )
-            }
-        }
+        // resample in scRGB and convert back, averaging gamma encoded values darkens fine detail
+        ( false, true ) => {
+            let interpretation = image.get_interpretation();
+            image.colourspace( Interpretation::Scrgb as i32 )
+                .and_then( | linear | resample_image( linear, hscale, vscale, kernel ) )
+                .and_then( | resized | resized.colourspace( interpretation ) )
+        },
+        ( false, false ) => resample_image( image, hscale, vscale, kernel ),
+    };
+
+    match result {
+        Ok( resized ) => Ok( resized ),
+        Err( err ) => Err( format!( "failed to resize image: {}", err ) )
     }
 }
 
-fn load_from_file( image_input: &ImageFile ) -> Result<VipsImage, String> {
-    match &image_input.pdf {
-        true => VipsImage::from_pdf_file( &image_input.path, &image_input.page, &image_input.n ),
-        false => VipsImage::from_file( &image_input.path )
-    }
+enum Fit {
+    Cover,
+    Contain,
+    Fill,
+    Inside,
+    Outside,
 }
 
-fn load_from_buffer( image_input: &ImageBytes ) -> Result<VipsImage, String> {
-    match &image_input.pdf {
-        true => VipsImage::from_pdf_buffer( &image_input.bytes, &image_input.page, &image_input.n ),
-        false => VipsImage::from_buffer( &image_input.bytes )
+fn atom_to_fit( resize_type: Atom ) -> Result<Fit, String> {
+    match resize_type {
+        resize_type if resize_type == atoms::auto() || resize_type == atoms::cover() => Ok( Fit::Cover ),
+        resize_type if resize_type == atoms::contain() => Ok( Fit::Contain ),
+        resize_type if resize_type == atoms::fill() => Ok( Fit::Fill ),
+        resize_type if resize_type == atoms::inside() => Ok( Fit::Inside ),
+        resize_type if resize_type == atoms::outside() => Ok( Fit::Outside ),
+        _ => Err( "resize type not supported".to_string() )
     }
 }
 
-#[rustler::nif]
-fn vips_process_file_to_file(image_input: ImageFile) -> NifResult<Atom> {
-    let result = match load_from_file( &image_input ) {
-        Ok( image ) => {
-            match resize_image( image, &image_input.resize ) {
-                Ok( image ) => save_image( &image, &image_input.save ),
-                Err( err ) => Err( err )
-            }
-        },
-        Err( err ) => Err( format!( "failed to open image: {}", err ) )
-    };
+fn validate_resize( resize: &ResizeOptions ) -> Result<(), String> {
+    let sized = resize.width != 0 || resize.height != 0;
 
-    match result {
-        Ok( () ) => Ok( ok() ),
-        Err( err ) => Err( Error::Term( Box::new( err ) ) )
+    if resize.width < 0 || resize.height < 0 {
+        Err( "width and height must not be negative".to_string() )
+    } else if resize.max_width < 0 || resize.max_height < 0 {
+        Err( "max_width and max_height must not be negative".to_string() )
+    } else if resize.longest_edge < 0 {
+        Err( "longest_edge must not be negative".to_string() )
+    } else if !resize.scale.is_finite() || resize.scale < 0.0 {
+        Err( "scale must be a positive number".to_string() )
+    } else if resize.scale != 0.0 && ( sized || resize.longest_edge != 0 ) {
+        Err( "scale can't be combined with width, height or longest_edge".to_string() )
+    } else if resize.longest_edge != 0 && sized {
+        Err( "longest_edge can't be combined with width or height".to_string() )
+    } else if let Some( ( x, y ) ) = resize.focal_point.filter( |( x, y )| !( 0.0..=1.0 ).contains( x ) || !( 0.0..=1.0 ).contains( y ) ) {
+        Err( format!( "focal_point must be within 0..1, got {{{}, {}}}", x, y ) )
+    } else {
+        Ok( () )
     }
 }
 
-#[rustler::nif]
-fn vips_process_file_to_bytes<'a>(env: Env<'a>, image_input: ImageFile) -> Result<Term<'a>, Error> {
-    let result = match load_from_file( &image_input ) {
-        Ok( image ) => {
-            match resize_image( image, &image_input.resize ) {
-                Ok( image ) => image_into_bytes( image, &image_input.save ),
-                Err( err ) => Err( err )
-            }
-        },
-        Err( err ) => Err( format!( "failed to open image: {}", err ) )
+// width and height requested for the output, 0 meaning the dimension follows the aspect ratio
+fn requested_size( source_width: i32, source_height: i32, resize: &ResizeOptions ) -> ( i32, i32 ) {
+    let ( width, height ) = match ( resize.scale, resize.longest_edge ) {
+        ( scale, _ ) if scale > 0.0 => (
+            ( ( source_width as f64 * scale ).round() as i32 ).max( 1 ),
+            ( ( source_height as f64 * scale ).round() as i32 ).max( 1 ),
+        ),
+        ( _, edge ) if edge > 0 && source_width >= source_height => ( edge, 0 ),
+        ( _, edge ) if edge > 0 => ( 0, edge ),
+        _ => ( resize.width, resize.height ),
     };
 
-    match result {
-        Ok( bytes ) => {
-            let mut own_binary = OwnedBinary::new( bytes.len() ).unwrap();
-            own_binary.as_mut_slice().copy_from_slice( &bytes );
-            let binary = Binary::from_owned( own_binary, env );
-            Ok( ( ok(), binary ).encode( env ) )
-        },
-        Err( err ) => Ok( ( error(), err ).encode( env ) )
+    if resize.max_width == 0 && resize.max_height == 0 {
+        return ( width, height );
     }
-}
 
-#[rustler::nif]
-fn vips_process_bytes_to_bytes<'a>(env: Env<'a>, image_input: ImageBytes) -> Result<Term<'a>, Error> {
-    let result = match load_from_buffer( &image_input ) {
-        Ok( image ) => {
-            match resize_image( image, &image_input.resize ) {
-                Ok( image ) => image_into_bytes( image, &image_input.save ),
-                Err( err ) => Err( err )
-            }
-        },
-        Err( err ) => Err( format!( "failed to open image: {}", err ) )
+    let ( full_width, full_height ) = match ( width, height ) {
+        ( 0, 0 ) => ( source_width as f64, source_height as f64 ),
+        ( width, 0 ) => ( width as f64, width as f64 * source_height as f64 / source_width as f64 ),
+        ( 0, height ) => ( height as f64 * source_width as f64 / source_height as f64, height as f64 ),
+        ( width, height ) => ( width as f64, height as f64 ),
     };
 
-    match result {
-        Ok( bytes ) => {
-            let mut binary = OwnedBinary::new( bytes.len() ).unwrap();
-            binary.as_mut_slice().copy_from_slice( &bytes );
-            let binary = Binary::from_owned( binary, env );
-            Ok( ( ok(), binary ).encode( env ) )
-        },
-        Err( err ) => Ok( ( error(), err ).encode( env ) )
+    let mut factor: f64 = 1.0;
+    if resize.max_width > 0 && full_width > resize.max_width as f64 {
+        factor = factor.min( resize.max_width as f64 / full_width );
+    }
+    if resize.max_height > 0 && full_height > resize.max_height as f64 {
+        factor = factor.min( resize.max_height as f64 / full_height );
+    }
+
+    let ( scaled_width, scaled_height ) = ( ( full_width * factor ).round() as i32, ( full_height * factor ).round() as i32 );
+    match ( width, height ) {
+        _ if factor >= 1.0 => ( width, height ),
+        // 0 means the original size to resize_image, no side given is ever scaled down to it
+        ( width, height ) if width > 0 && height > 0 => ( scaled_width.max( 1 ), scaled_height.max( 1 ) ),
+        // the missing side follows the aspect ratio, unless one of them has to be kept at a pixel,
+        // both are given then so neither goes past its maximum
+        _ if scaled_width < 1 || scaled_height < 1 => ( scaled_width.max( 1 ), scaled_height.max( 1 ) ),
+        ( _, 0 ) => ( scaled_width, 0 ),
+        _ => ( 0, scaled_height ),
     }
 }
 
-#[rustler::nif]
-fn vips_process_bytes_to_file<'a>(env: Env<'a>, image_input: ImageBytes) -> Result<Term<'a>, Error> {
-    let result = match load_from_buffer( &image_input ) {
+fn resize_image(image: VipsImage, resize: &ResizeOptions, background: &[f64], guard: &Guard) -> Result<( VipsImage, Option<Smartcrop> ), String> {
+    if let Err( err ) = validate_resize( resize ) {
+        return Err( err );
+    }
+
+    let source_width = image.get_width();
+    let source_height = image.get_height();
+
+    let ( target_width, target_height ) = requested_size( source_width, source_height, resize );
+
+    let original_size = ( target_width == 0 && target_height == 0 ) ||
+        ( target_width == source_width && target_height == source_height ) ||
+        ( target_width == source_width && target_height == 0 ) ||
+        ( target_height == source_height && source_width == 0 );
+
+    if original_size {
+        Ok( ( image, None ) )
+    } else {
+        let fit = match atom_to_fit( resize.resize_type ) {
+            Ok( fit ) => fit,
+            Err( err ) => return Err( err )
+        };
+
+        // a focal point takes over from the gravity
+        let gravity = match resize.focal_point {
+            Some( ( x, y ) ) => CropGravity::Focal( x, y ),
+            None => match atom_to_gravity( resize.gravity ) {
+                Ok( gravity ) => gravity,
+                Err( err ) => return Err( err )
+            }
+        };
+
+        let source_ratio = source_width as f64 / source_height as f64;
+
+        let mut target_width_f64 = ( target_height as f64 * source_width as f64 / source_height as f64 ) * ( target_width == 0 ) as i32 as f64 +
+            target_width as f64 * ( target_width != 0 ) as i32 as f64;
+        let mut target_height_f64 = ( target_width as f64 * source_height as f64 / source_width as f64 ) * ( target_height == 0 ) as i32 as f64 +
+            target_height as f64 * ( target_height != 0 ) as i32 as f64;
+
+        if resize.without_enlargement {
+            // shrink the target box until the fit no longer scales any axis up
+            let enlargement = match fit {
+                Fit::Contain | Fit::Inside => ( target_width_f64 / source_width as f64 ).min( target_height_f64 / source_height as f64 ),
+                Fit::Cover | Fit::Fill | Fit::Outside => ( target_width_f64 / source_width as f64 ).max( target_height_f64 / source_height as f64 ),
+            };
+
+            if enlargement > 1.0 {
+                target_width_f64 = ( target_width_f64 / enlargement ).round().max( 1.0 );
+                target_height_f64 = ( target_height_f64 / enlargement ).round().max( 1.0 );
+            }
+
+            if target_width_f64 as i32 == source_width && target_height_f64 as i32 == source_height {
+                return Ok( ( image, None ) );
+            }
+        }
+
+        let target_ratio = target_width_f64 / target_height_f64;
+
+        // scale at which the image covers the whole target box, rounded up so the crop never runs short
+        let cover_width =
+                source_width as f64 * target_height_f64 / source_height as f64  * ( source_ratio >= target_ratio ) as i32 as f64 +
+                target_width_f64 * ( source_ratio < target_ratio ) as i32 as f64;
+        let cover_scale = cover_width.ceil() / source_width as f64;
+
+        // scale at which the image fits inside the target box
+        let inside_scale = match source_ratio >= target_ratio {
+            true => target_width_f64 / source_width as f64,
+            false => target_height_f64 / source_height as f64,
+        };
+
+        match fit {
+            Fit::Cover => {
+                match scale_image( image, cover_scale, cover_scale, resize, guard ) {
+                    Ok( resized ) => crop_to_gravity( resized, target_width_f64 as i32, target_height_f64 as i32, &gravity, guard ),
+                    Err( err ) => Err( err )
+                }
+            },
+            Fit::Contain => {
+                match scale_image( image, inside_scale, inside_scale, resize, guard ) {
+                    Ok( resized ) => {
+                        let options = gravity_options( &resized, background );
+                        // compass gravities place the image, smart strategies have nothing to crop and keep it centred
+                        let direction = match gravity {
+                            CropGravity::Compass( direction ) => direction,
+                            CropGravity::Smart( _ ) | CropGravity::Focal( _, _ ) => CompassDirection::Centre,
+                        };
+                        match resized.gravity( direction, target_width_f64 as i32, target_height_f64 as i32, &options ) {
+                            Ok( padded ) => Ok( ( padded, None ) ),
+                            Err( err ) => Err( format!( "failed to pad image: {}", err ) )
+                        }
+                    },
+                    Err( err ) => Err( err )
+                }
+            },
+            Fit::Fill => {
+                scale_image( image, target_width_f64 / source_width as f64, target_height_f64 / source_height as f64, resize, guard )
+                    .map( |resized| ( resized, None ) )
+            },
+            Fit::Inside => {
+                scale_image( image, inside_scale, inside_scale, resize, guard )
+                    .map( |resized| ( resized, None ) )
+            },
+            Fit::Outside => {
+                scale_image( image, cover_scale, cover_scale, resize, guard )
+                    .map( |resized| ( resized, None ) )
+            },
+        }
+
+    }
+
+}
+
+fn crop_image( image: VipsImage, crop: &CropOptions ) -> Result<VipsImage, String> {
+    let width = image.get_width() as i64;
+    let height = image.get_height() as i64;
+
+    if crop.width <= 0 || crop.height <= 0 {
+        Err( "crop width and height must be positive".to_string() )
+    } else if crop.left < 0 || crop.top < 0 || crop.left as i64 + crop.width as i64 > width || crop.top as i64 + crop.height as i64 > height {
+        Err( format!(
+            "crop area {}x{} at {},{} is outside the image bounds {}x{}",
+            crop.width, crop.height, crop.left, crop.top, width, height
+        ) )
+    } else {
+        match image.crop( crop.left, crop.top, crop.width, crop.height ) {
+            Ok( cropped ) => Ok( cropped ),
+            Err( err ) => Err( format!( "failed to crop image: {}", err ) )
+        }
+    }
+}
+
+fn atom_to_interpolate( interpolate: Atom ) -> Result<Interpolate, String> {
+    match interpolate {
+        interpolate if interpolate == atoms::nearest() => Ok( Interpolate::Nearest ),
+        interpolate if interpolate == atoms::bilinear() => Ok( Interpolate::Bilinear ),
+        interpolate if interpolate == atoms::bicubic() => Ok( Interpolate::Bicubic ),
+        interpolate if interpolate == atoms::lbb() => Ok( Interpolate::Lbb ),
+        interpolate if interpolate == atoms::nohalo() => Ok( Interpolate::Nohalo ),
+        interpolate if interpolate == atoms::vsqbs() => Ok( Interpolate::Vsqbs ),
+        _ => Err( "interpolate not supported".to_string() )
+    }
+}
+
+fn affine_image( image: VipsImage, affine: &AffineOptions ) -> Result<VipsImage, String> {
+    let matrix = match affine.matrix[..] {
+        [ a, b, c, d ] if ( a * d - b * c ).abs() > f64::EPSILON => [ a, b, c, d ],
+        [ _, _, _, _ ] => return Err( "affine matrix is not invertible".to_string() ),
+        _ => return Err( "affine matrix must be a list of 4 numbers".to_string() ),
+    };
+
+    let interpolate = match atom_to_interpolate( affine.interpolate ) {
+        Ok( interpolate ) => interpolate,
+        Err( err ) => return Err( err )
+    };
+
+    let options = VipsAffineOptions {
+        interpolate: interpolate,
+        odx: affine.odx,
+        ody: affine.ody,
+        idx: affine.idx,
+        idy: affine.idy,
+        background: match affine.background.len() {
+            0 => VipsAffineOptions::default().background,
+            _ => background_for( &image, &affine.background ),
+        },
+    };
+
+    match image.affine( matrix, &options ) {
+        Ok( transformed ) => Ok( transformed ),
+        Err( err ) => Err( format!( "failed to transform image: {}", err ) )
+    }
+}
+
+fn distance( a: ( f64, f64 ), b: ( f64, f64 ) ) -> f64 {
+    ( ( a.0 - b.0 ).powi( 2 ) + ( a.1 - b.1 ).powi( 2 ) ).sqrt()
+}
+
+// row-major 3x3 homography taking output pixels of a width x height rectangle
+// to the source quad given as top-left, top-right, bottom-right, bottom-left (Heckbert's square to quad)
+fn homography( corners: &[( f64, f64 ); 4], width: i32, height: i32 ) -> Result<[f64; 9], String> {
+    let [ ( x0, y0 ), ( x1, y1 ), ( x2, y2 ), ( x3, y3 ) ] = *corners;
+
+    let dx1 = x1 - x2;
+    let dx2 = x3 - x2;
+    let dx3 = x0 - x1 + x2 - x3;
+    let dy1 = y1 - y2;
+    let dy2 = y3 - y2;
+    let dy3 = y0 - y1 + y2 - y3;
+
+    let ( g, h ) = match ( dx3, dy3 ) {
+        // a parallelogram, the mapping is affine
+        ( dx3, dy3 ) if dx3 == 0.0 && dy3 == 0.0 => ( 0.0, 0.0 ),
+        ( dx3, dy3 ) => {
+            let den = dx1 * dy2 - dx2 * dy1;
+            if den.abs() <= f64::EPSILON {
+                return Err( "perspective corners must form a quadrilateral".to_string() );
+            }
+            ( ( dx3 * dy2 - dx2 * dy3 ) / den, ( dx1 * dy3 - dx3 * dy1 ) / den )
+        }
+    };
+
+    let a = x1 - x0 + g * x1;
+    let b = x3 - x0 + h * x3;
+    let d = y1 - y0 + g * y1;
+    let e = y3 - y0 + h * y3;
+
+    if ( a * e - b * d ).abs() <= f64::EPSILON {
+        return Err( "perspective corners must form a quadrilateral".to_string() );
+    }
+
+    // scale the unit square up to the output size
+    let ( out_width, out_height ) = ( width as f64, height as f64 );
+    Ok( [
+        a / out_width, b / out_height, x0,
+        d / out_width, e / out_height, y0,
+        g / out_width, h / out_height, 1.0,
+    ] )
+}
+
+fn perspective_image( image: VipsImage, perspective: &PerspectiveOptions ) -> Result<VipsImage, String> {
+    let corners: [( f64, f64 ); 4] = match perspective.corners[..] {
+        [ tl, tr, br, bl ] => [ tl, tr, br, bl ],
+        _ => return Err( "perspective needs 4 corners".to_string() ),
+    };
+
+    if perspective.width < 0 || perspective.height < 0 {
+        return Err( "perspective width and height must not be negative".to_string() );
+    }
+
+    // by default the output keeps the longest edges of the quad
+    let width = match perspective.width {
+        0 => distance( corners[0], corners[1] ).max( distance( corners[3], corners[2] ) ).round() as i32,
+        width => width,
+    };
+    let height = match perspective.height {
+        0 => distance( corners[0], corners[3] ).max( distance( corners[1], corners[2] ) ).round() as i32,
+        height => height,
+    };
+
+    if width <= 0 || height <= 0 {
+        return Err( "perspective corners must form a quadrilateral".to_string() );
+    }
+
+    let matrix = match homography( &corners, width, height ) {
+        Ok( matrix ) => matrix,
+        Err( err ) => return Err( err )
+    };
+
+    let interpolate = match atom_to_interpolate( perspective.interpolate ) {
+        Ok( interpolate ) => interpolate,
+        Err( err ) => return Err( err )
+    };
+
+    let options = MapimOptions {
+        interpolate: interpolate,
+        background: match perspective.background.len() {
+            0 => MapimOptions::default().background,
+            _ => background_for( &image, &perspective.background ),
+        },
+    };
+
+    // index image of source coordinates, ( x, y, w ) = H * ( u, v, 1 ) then divided by w
+    let index = VipsImage::xyz( width, height )
+        .and_then( |xy| xy.bandjoin_const( 1.0 ) )
+        .and_then( |uv1| uv1.recomb( &matrix, 3, 3 ) )
+        .and_then( |xyw| {
+            let xy = xyw.extract_bands( 0, 2 )?;
+            let w = xyw.extract_bands( 2, 1 )?;
+            xy.divide( &w )
+        } );
+
+    match index.and_then( |index| image.mapim( &index, &options ) ) {
+        Ok( rectified ) => Ok( rectified ),
+        Err( err ) => Err( format!( "failed to correct perspective: {}", err ) )
+    }
+}
+
+fn rotate_image( image: VipsImage, rotate: &RotateOptions ) -> Result<VipsImage, String> {
+    let angle = rotate.angle.rem_euclid( 360.0 );
+
+    // multiples of 90 are a lossless pixel shuffle
+    let result = match angle {
+        angle if angle == 0.0 => Ok( image ),
+        angle if angle == 90.0 => image.rot( Angle::D90 ),
+        angle if angle == 180.0 => image.rot( Angle::D180 ),
+        angle if angle == 270.0 => image.rot( Angle::D270 ),
+        angle => {
+            let options = match rotate.background.len() {
+                0 => VipsRotateOptions::default(),
+                _ => VipsRotateOptions {
+                    background: background_for( &image, &rotate.background ),
+                }
+            };
+            image.rotate_opts( angle, &options )
+        }
+    };
+
+    match result {
+        Ok( rotated ) => Ok( rotated ),
+        Err( err ) => Err( format!( "failed to rotate image: {}", err ) )
+    }
+}
+
+fn flip_image( image: VipsImage, direction: Direction ) -> Result<VipsImage, String> {
+    match image.flip( direction ) {
+        Ok( flipped ) => Ok( flipped ),
+        Err( err ) => Err( format!( "failed to flip image: {}", err ) )
+    }
+}
+
+fn embed_image( image: VipsImage, embed: &EmbedOptions, background: &[f64] ) -> Result<VipsImage, String> {
+    if embed.width <= 0 || embed.height <= 0 {
+        return Err( "embed width and height must be positive".to_string() );
+    }
+
+    let direction = match atom_to_direction( embed.gravity ) {
+        Ok( direction ) => direction,
+        Err( err ) => return Err( err )
+    };
+
+    // the canvas has its own color, falling back to the save background
+    let background = match embed.background.len() {
+        0 => background,
+        _ => &embed.background[..],
+    };
+    let options = gravity_options( &image, background );
+
+    match image.gravity( direction, embed.width, embed.height, &options ) {
+        Ok( padded ) => Ok( padded ),
+        Err( err ) => Err( format!( "failed to embed image: {}", err ) )
+    }
+}
+
+// bounding box of the content, as [ left, top, width, height ]
+fn trim_box( image: &VipsImage, trim: &TrimOptions, guard: &Guard ) -> Result<[i32; 4], String> {
+    if !trim.threshold.is_finite() || trim.threshold < 0.0 {
+        return Err( "trim threshold must be a positive number".to_string() );
+    }
+
+    // reading the background pixel may decode the image as much as finding the trim does
+    guard.run( image, |image| {
+        // by default the top-left pixel is the background, images with alpha are flattened against it
+        // by vips_find_trim, so transparent margins are trimmed as well
+        let background = match trim.background.len() {
+            0 => match image.get_point( 0, 0 ) {
+                Ok( mut point ) => {
+                    if image.has_alpha() {
+                        point.pop();
+                    }
+                    point
+                },
+                Err( err ) => return Err( format!( "failed to read background: {}", err ) )
+            },
+            _ => trim.background.to_owned(),
+        };
+
+        let options = FindTrimOptions {
+            threshold: trim.threshold,
+            background: background,
+        };
+
+        match image.find_trim( &options ) {
+            Ok( trim_box ) => Ok( trim_box ),
+            Err( err ) => Err( format!( "failed to find trim: {}", err ) )
+        }
+    } )
+}
+
+fn trim_image( image: VipsImage, trim: &TrimOptions, guard: &Guard ) -> Result<VipsImage, String> {
+    match trim_box( &image, trim, guard ) {
+        // nothing but background, keep the image as it is
+        Ok( [ _, _, width, height ] ) if width == 0 || height == 0 => Ok( image ),
+        Ok( [ left, top, width, height ] ) => match image.crop( left, top, width, height ) {
+            Ok( trimmed ) => Ok( trimmed ),
+            Err( err ) => Err( format!( "failed to trim image: {}", err ) )
+        },
+        Err( err ) => Err( err )
+    }
+}
+
+fn atom_to_blend( blend: Atom ) -> Result<BlendMode, String> {
+    match blend {
+        blend if blend == atoms::over() => Ok( BlendMode::Over ),
+        blend if blend == atoms::add() => Ok( BlendMode::Add ),
+        blend if blend == atoms::saturate() => Ok( BlendMode::Saturate ),
+        blend if blend == atoms::multiply() => Ok( BlendMode::Multiply ),
+        blend if blend == atoms::screen() => Ok( BlendMode::Screen ),
+        blend if blend == atoms::overlay() => Ok( BlendMode::Overlay ),
+        blend if blend == atoms::darken() => Ok( BlendMode::Darken ),
+        blend if blend == atoms::lighten() => Ok( BlendMode::Lighten ),
+        blend if blend == atoms::colour_dodge() => Ok( BlendMode::ColourDodge ),
+        blend if blend == atoms::colour_burn() => Ok( BlendMode::ColourBurn ),
+        blend if blend == atoms::hard_light() => Ok( BlendMode::HardLight ),
+        blend if blend == atoms::soft_light() => Ok( BlendMode::SoftLight ),
+        blend if blend == atoms::difference() => Ok( BlendMode::Difference ),
+        blend if blend == atoms::exclusion() => Ok( BlendMode::Exclusion ),
+        _ => Err( "blend mode not supported".to_string() )
+    }
+}
+
+fn blur_image( image: VipsImage, blur: &BlurOptions ) -> Result<VipsImage, String> {
+    if !blur.sigma.is_finite() || blur.sigma <= 0.0 {
+        return Err( "blur sigma must be a positive number".to_string() );
+    }
+
+    match image.gaussblur( blur.sigma ) {
+        Ok( blurred ) => Ok( blurred ),
+        Err( err ) => Err( format!( "failed to blur image: {}", err ) )
+    }
+}
+
+// the overlay is checked against the limits of the image it goes onto
+fn load_overlay( composite: &CompositeOptions, guard: &Guard ) -> Result<VipsImage, LoadError> {
+    let options = load_options( guard.limits )?;
+    let overlay = match ( &composite.path, &composite.bytes ) {
+        ( Some( path ), None ) => {
+            if let Ok( metadata ) = fs::metadata( path ) {
+                check_input_bytes( guard.limits, metadata.len() )?;
+            }
+            VipsImage::from_file_opts( path, &options )
+        },
+        ( None, Some( bytes ) ) => {
+            check_input_bytes( guard.limits, bytes.len() as u64 )?;
+            VipsImage::from_buffer_opts( bytes, &options )
+        },
+        _ => return Err( LoadError::Failed( "composite needs either a path or bytes".to_string() ) ),
+    };
+    limit_image( overlay, guard.limits, true )
+}
+
+fn composite_image( image: VipsImage, composite: &CompositeOptions, guard: &Guard ) -> Result<VipsImage, String> {
+    let overlay = match load_overlay( composite, guard ) {
+        Ok( overlay ) => overlay,
+        Err( err ) => return Err( format!( "failed to open overlay: {}", err.message() ) )
+    };
+
+    let direction = match atom_to_direction( composite.gravity ) {
+        Ok( direction ) => direction,
+        Err( err ) => return Err( err )
+    };
+
+    let mode = match atom_to_blend( composite.blend ) {
+        Ok( mode ) => mode,
+        Err( err ) => return Err( err )
+    };
+
+    let ( left, top ) = gravity_offset( direction, image.get_width(), image.get_height(), overlay.get_width(), overlay.get_height() );
+
+    match image.composite2( &overlay, mode, left + composite.left, top + composite.top ) {
+        Ok( composited ) => Ok( composited ),
+        Err( err ) => Err( format!( "failed to composite image: {}", err ) )
+    }
+}
+
+impl<'a> Operation<'a> {
+    fn name( &self ) -> &'static str {
+        match self {
+            Operation::Resize( _ ) => "resize",
+            Operation::Crop( _ ) => "crop",
+            Operation::Trim( _ ) => "trim",
+            Operation::Rotate( _ ) => "rotate",
+            Operation::Flip( _ ) => "flip",
+            Operation::Embed( _ ) => "embed",
+            Operation::Affine( _ ) => "affine",
+            Operation::Perspective( _ ) => "perspective",
+            Operation::Blur( _ ) => "blur",
+            Operation::Composite( _ ) => "composite",
+        }
+    }
+
+    fn apply( &self, image: VipsImage, background: &[f64], guard: &Guard ) -> Result<( VipsImage, Option<Smartcrop> ), String> {
+        let result = match self {
+            Operation::Resize( resize ) => return resize_image( image, resize, background, guard ),
+            Operation::Crop( crop ) => crop_image( image, crop ),
+            Operation::Trim( trim ) => trim_image( image, trim, guard ),
+            Operation::Rotate( rotate ) => rotate_image( image, rotate ),
+            Operation::Flip( direction ) if *direction == atoms::horizontal() => flip_image( image, Direction::Horizontal ),
+            Operation::Flip( direction ) if *direction == atoms::vertical() => flip_image( image, Direction::Vertical ),
+            Operation::Flip( _ ) => Err( "flip direction not supported".to_string() ),
+            Operation::Embed( embed ) => embed_image( image, embed, background ),
+            Operation::Affine( affine ) => affine_image( image, affine ),
+            Operation::Perspective( perspective ) => perspective_image( image, perspective ),
+            Operation::Blur( blur ) => blur_image( image, blur ),
+            Operation::Composite( composite ) => composite_image( image, composite, guard ),
+        };
+
+        result.map( |image| ( image, None ) )
+    }
+}
+
+// what the processing was given to protect itself, applied to every step and not only to the first load
+struct Guard<'g> {
+    limits: &'g Option<LoadLimits>,
+    deadline: Option<Instant>,
+    job: Option<&'g Job>,
+}
+
+impl<'g> Guard<'g> {
+    fn check( &self ) -> Result<(), String> {
+        match self.deadline {
+            Some( deadline ) if Instant::now() >= deadline => Err( "timeout".to_string() ),
+            _ => Ok( () )
+        }
+    }
+
+    // for the steps computing pixels before the encoding, a watchdog kills the computation once the deadline passes,
+    // not the eval signal, which would stay on every image built from this one and hide the encoding from Watch::run,
+    // the kill goes on a private copy so the image shared with other processings is left alone
+    fn run<T, F>( &self, image: &VipsImage, compute: F ) -> Result<T, String> where F: FnOnce( &VipsImage ) -> Result<T, String> {
+        let deadline = match self.deadline {
+            Some( deadline ) => deadline,
+            None => return compute( image )
+        };
+        self.check()?;
+
+        let image = image.copy()?;
+        let image = &image;
+        let ( done, finished ) = mpsc::channel::<()>();
+        thread::scope( |scope| {
+            scope.spawn( move || {
+                if let Err( mpsc::RecvTimeoutError::Timeout ) = finished.recv_timeout( deadline.saturating_duration_since( Instant::now() ) ) {
+                    image.set_kill( true );
+                }
+            } );
+            let result = compute( image );
+            drop( done );
+            result
+        } )
+    }
+
+    // the image every later step computes from, registered with the job so cancelling it stops them all
+    fn track( &self, image: VipsImage ) -> Result<VipsImage, String> {
+        match self.job {
+            Some( job ) => job.track( &image ),
+            None => Ok( image )
+        }
+    }
+}
+
+// crop options split by the stage they run at, before and after resize
+fn crop_stages<'a>( pipeline: &Pipeline<'a, '_> ) -> Result<( Option<&'a CropOptions>, Option<&'a CropOptions> ), String> {
+    match pipeline.crop {
+        Some( crop ) if crop.stage == atoms::before() => Ok( ( Some( crop ), None ) ),
+        Some( crop ) if crop.stage == atoms::after() => Ok( ( None, Some( crop ) ) ),
+        Some( _ ) => Err( "crop stage not supported".to_string() ),
+        None => Ok( ( None, None ) ),
+    }
+}
+
+impl<'a, 'b> Pipeline<'a, 'b> {
+    fn guard( &self ) -> Guard<'a> {
+        Guard {
+            limits: self.limits,
+            deadline: self.deadline,
+            job: self.job,
+        }
+    }
+
+    // the fixed options are a shorthand for operations, in the order they have always run,
+    // these are the ones before resize, shared by every output of the image
+    fn prepare_operations( &self ) -> Result<Vec<Operation<'b>>, String> {
+        let mut operations = Vec::new();
+        if let ( Some( crop ), _ ) = crop_stages( self )? {
+            operations.push( Operation::Crop( crop.clone() ) );
+        }
+        if let Some( trim ) = self.trim {
+            operations.push( Operation::Trim( trim.clone() ) );
+        }
+        if let Some( perspective ) = self.perspective {
+            operations.push( Operation::Perspective( perspective.clone() ) );
+        }
+        if let Some( affine ) = self.affine {
+            operations.push( Operation::Affine( affine.clone() ) );
+        }
+        if let Some( rotate ) = self.rotate {
+            operations.push( Operation::Rotate( rotate.clone() ) );
+        }
+        if self.flip_horizontal {
+            operations.push( Operation::Flip( atoms::horizontal() ) );
+        }
+        if self.flip_vertical {
+            operations.push( Operation::Flip( atoms::vertical() ) );
+        }
+        Ok( operations )
+    }
+
+    // resize to the given options and the fixed steps following it
+    fn finish_operations( &self, resize: &ResizeOptions ) -> Result<Vec<Operation<'b>>, String> {
+        let mut operations = vec![ Operation::Resize( resize.clone() ) ];
+        if let ( _, Some( crop ) ) = crop_stages( self )? {
+            operations.push( Operation::Crop( crop.clone() ) );
+        }
+        if let Some( embed ) = self.embed {
+            operations.push( Operation::Embed( embed.clone() ) );
+        }
+        Ok( operations )
+    }
+}
+
+// the last smart crop made is the one reported
+fn apply_operations( image: VipsImage, operations: &[Operation], background: &[f64], guard: &Guard ) -> Result<( VipsImage, Option<Smartcrop> ), String> {
+    let mut image = image;
+    let mut smartcrop = None;
+    for operation in operations {
+        guard.check()?;
+        let ( processed, crop ) = operation.apply( image, background, guard )?;
+        image = processed;
+        smartcrop = crop.or( smartcrop );
+    }
+    Ok( ( image, smartcrop ) )
+}
+
+// returns the processed image along with the smart crop, if one was made
+fn transform_image( image: VipsImage, pipeline: &Pipeline ) -> Result<( VipsImage, Option<Smartcrop> ), String> {
+    match prepare_image( image, pipeline ) {
+        Ok( prepared ) => finish_image( prepared, pipeline, pipeline.resize, pipeline.save ),
+        Err( err ) => Err( err )
+    }
+}
+
+// the steps running before resize, shared by every output of the image
+fn prepare_image( image: VipsImage, pipeline: &Pipeline ) -> Result<VipsImage, String> {
+    let guard = pipeline.guard();
+    let image = guard.track( image )?;
+    let operations = pipeline.prepare_operations()?;
+    apply_operations( image, &operations, &pipeline.save.background, &guard ).map( |( image, _ )| image )
+}
+
+// resize and the steps following it for the given output size and save options, then the operations list,
+// which can order every step itself when the fixed options are left out
+fn finish_image( image: VipsImage, pipeline: &Pipeline, resize: &ResizeOptions, save: &SaveOptions ) -> Result<( VipsImage, Option<Smartcrop> ), String> {
+    let guard = pipeline.guard();
+    let operations = pipeline.finish_operations( resize )?;
+    let ( mut image, mut smartcrop ) = apply_operations( image, &operations, &save.background, &guard )?;
+
+    for ( index, operation ) in pipeline.operations.iter().enumerate() {
+        guard.check()?;
+        match operation.apply( image, &save.background, &guard ) {
+            Ok( ( processed, crop ) ) => {
+                image = processed;
+                smartcrop = crop.or( smartcrop );
+            },
+            Err( err ) => return Err( format!( "operation {} ({}) failed: {}", index, operation.name(), err ) )
+        }
+    }
+
+    Ok( ( image, smartcrop ) )
+}
+
+fn save_image( image: &VipsImage, save_options: &SaveOptions ) -> Result<(), String> {
+    let result = match saver( image, save_options )? {
+        Saver::Jpeg( options ) => image.save_jpeg_opts( &save_options.path, &options ),
+        Saver::Png( options ) => image.save_png_opts( &save_options.path, &options ),
+        Saver::WebP( options ) => image.save_webp_opts( &save_options.path, &options ),
+        Saver::Avif( options ) => image.save_heif_opts( &save_options.path, &options ),
+        Saver::Svg => image.save_svg( &save_options.path ),
+    };
+
+    match result {
+        Ok( () ) => Ok( () ),
+        Err( err ) => Err( format!( "failed to save image: {}", err ) )
+    }
+}
+
+// same encoders as image_into_bytes, the chunks go to the target as libvips produces them
+fn image_into_target( image: &VipsImage, save_options: &SaveOptions, target: &VipsTarget ) -> Result<(), String> {
+    let result = match saver( image, save_options )? {
+        Saver::Jpeg( options ) => image.jpeg_target_opts( target, &options ),
+        Saver::Png( options ) => image.png_target_opts( target, &options ),
+        Saver::WebP( options ) => image.webp_target_opts( target, &options ),
+        Saver::Avif( options ) => image.avif_target_opts( target, &options ),
+        Saver::Svg => return Err( "svg can not be streamed".to_string() )
+    };
+
+    match result {
+        Ok( () ) => Ok( () ),
+        Err( err ) => Err( format!( "failed to save image: {}", err ) )
+    }
+}
+
+fn autorotate_image( image: Result<VipsImage, String>, autorotate: bool ) -> Result<VipsImage, String> {
+    match ( image, autorotate ) {
+        ( Ok( image ), true ) => image.autorot(),
+        ( image, _ ) => image,
+    }
+}
+
+// reported to Elixir as {:limit_exceeded, limit, value, max}
+struct LimitExceeded {
+    limit: &'static str,
+    value: u64,
+    max: u64,
+}
+
+impl Encoder for LimitExceeded {
+    fn encode<'a>( &self, env: Env<'a> ) -> Term<'a> {
+        ( atoms::limit_exceeded(), Atom::from_str( env, self.limit ).unwrap(), self.value, self.max ).encode( env )
+    }
+}
+
+enum LoadError {
+    Failed( String ),
+    Limit( LimitExceeded ),
+}
+
+impl LoadError {
+    fn message( &self ) -> String {
+        match self {
+            LoadError::Failed( err ) => err.to_owned(),
+            LoadError::Limit( limit ) => format!( "{} exceeded: {} > {}", limit.limit, limit.value, limit.max ),
+        }
+    }
+}
+
+fn check_limit( limit: &'static str, value: u64, max: Option<u64> ) -> Result<(), LoadError> {
+    match max {
+        Some( max ) if value > max => Err( LoadError::Limit( LimitExceeded { limit: limit, value: value, max: max } ) ),
+        _ => Ok( () )
+    }
+}
+
+fn check_input_bytes( limits: &Option<LoadLimits>, size: u64 ) -> Result<(), LoadError> {
+    match limits {
+        Some( limits ) => check_limit( "max_bytes", size, limits.max_bytes ),
+        None => Ok( () )
+    }
+}
+
+// the loaders have only read the header at this point, nothing is decoded yet
+fn check_image_limits( image: &VipsImage, limits: &Option<LoadLimits> ) -> Result<(), LoadError> {
+    let limits = match limits {
+        Some( limits ) => limits,
+        None => return Ok( () )
+    };
+
+    let ( width, height ) = ( image.get_width() as u64, image.get_height() as u64 );
+    check_limit( "max_width", width, limits.max_width )?;
+    check_limit( "max_height", height, limits.max_height )?;
+    check_limit( "max_pixels", width * height, limits.max_pixels )?;
+    check_limit( "max_pages", image.get_pages() as u64, limits.max_pages )
+}
+
+fn atom_to_fail_on( fail_on: Atom ) -> Result<FailOn, String> {
+    match fail_on {
+        fail_on if fail_on == atoms::none() => Ok( FailOn::None ),
+        fail_on if fail_on == atoms::truncated() => Ok( FailOn::Truncated ),
+        fail_on if fail_on == error() => Ok( FailOn::Error ),
+        fail_on if fail_on == atoms::warning() => Ok( FailOn::Warning ),
+        _ => Err( "fail_on not supported".to_string() )
+    }
+}
+
+fn load_options( limits: &Option<LoadLimits> ) -> Result<LoadOptions, LoadError> {
+    match limits {
+        Some( limits ) => match atom_to_fail_on( limits.fail_on ) {
+            Ok( fail_on ) => Ok( LoadOptions { fail_on: fail_on } ),
+            Err( err ) => Err( LoadError::Failed( err ) )
+        },
+        None => Ok( LoadOptions::default() )
+    }
+}
+
+fn limit_image( image: Result<VipsImage, String>, limits: &Option<LoadLimits>, autorotate: bool ) -> Result<VipsImage, LoadError> {
+    let image = match image {
+        Ok( image ) => image,
+        Err( err ) => return Err( LoadError::Failed( err ) )
+    };
+
+    check_image_limits( &image, limits )?;
+    autorotate_image( Ok( image ), autorotate ).map_err( LoadError::Failed )
+}
+
+fn load_from_file( image_input: &ImageFile ) -> Result<VipsImage, LoadError> {
+    if let Some( handle ) = &image_input.handle {
+        return Ok( handle.shared() );
+    }
+
+    // a missing file is left for the loader to report
+    if let Ok( metadata ) = fs::metadata( &image_input.path ) {
+        check_input_bytes( &image_input.limits, metadata.len() )?;
+    }
+
+    let options = load_options( &image_input.limits )?;
+    let image = match &image_input.pdf {
+        true => VipsImage::from_pdf_file( &image_input.path, &image_input.page, &image_input.n, &options ),
+        false => VipsImage::from_file_opts( &image_input.path, &options )
+    };
+    limit_image( image, &image_input.limits, image_input.autorotate )
+}
+
+fn load_from_buffer( image_input: &ImageBytes ) -> Result<VipsImage, LoadError> {
+    if let Some( handle ) = &image_input.handle {
+        return Ok( handle.shared() );
+    }
+
+    check_input_bytes( &image_input.limits, image_input.bytes.len() as u64 )?;
+
+    let options = load_options( &image_input.limits )?;
+    let image = match &image_input.pdf {
+        true => VipsImage::from_pdf_buffer( &image_input.bytes, &image_input.page, &image_input.n, &options ),
+        false => VipsImage::from_buffer_opts( &image_input.bytes, &options )
+    };
+    limit_image( image, &image_input.limits, image_input.autorotate )
+}
+
+// the only copy of the encoded image, the libvips buffer is freed right after
+fn into_binary( bytes: &[u8] ) -> Result<OwnedBinary, String> {
+    match OwnedBinary::new( bytes.len() ) {
+        Some( mut binary ) => {
+            binary.as_mut_slice().copy_from_slice( bytes );
+            Ok( binary )
+        },
+        None => Err( "failed to allocate binary".to_string() )
+    }
+}
+
+// deadline and progress reports of one processing, from timeout_ms and progress of ImageFile / ImageBytes,
+// progress is reported for the encoding, the steps before it are held to the same deadline by the guard
+struct Watch<'w> {
+    deadline: Option<Instant>,
+    timed_out: bool,
+    percent: i32,
+    report: Option<Box<dyn FnMut( i32 ) + 'w>>,
+}
+
+impl<'w> Watch<'w> {
+    fn new( deadline: Option<Instant>, report: Option<Box<dyn FnMut( i32 ) + 'w>> ) -> Watch<'w> {
+        Watch {
+            deadline: deadline,
+            timed_out: false,
+            percent: -1,
+            report: report,
+        }
+    }
+
+    fn expired( &mut self ) -> bool {
+        if let Some( deadline ) = self.deadline {
+            self.timed_out = self.timed_out || Instant::now() >= deadline;
+        }
+        self.timed_out
+    }
+
+    fn tick( &mut self, percent: i32 ) -> bool {
+        if self.expired() {
+            return false;
+        }
+        if percent != self.percent {
+            self.percent = percent;
+            if let Some( report ) = self.report.as_mut() {
+                report( percent );
+            }
+        }
+        true
+    }
+
+    // the signal goes on a copy, a loaded image may be encoded by several processings at once
+    fn run<T, F>( &mut self, image: &VipsImage, encode: F ) -> Result<T, String> where F: FnOnce( &VipsImage ) -> Result<T, String> {
+        if self.deadline.is_none() && self.report.is_none() {
+            return encode( image );
+        }
+        if self.expired() {
+            return Err( "timeout".to_string() );
+        }
+
+        let image = image.copy()?;
+        let result = image.watch_eval( &mut |percent| self.tick( percent ), || encode( &image ) );
+        if result.is_ok() {
+            self.tick( 100 );
+        }
+        result
+    }
+}
+
+// libvips reports progress from the thread running the computation, for the NIFs that is the calling process
+fn progress_report<'a>( env: Env<'a>, progress: &Option<ProgressOptions<'a>> ) -> Option<Box<dyn FnMut( i32 ) + 'a>> {
+    match progress {
+        Some( progress ) => {
+            let ( pid, tag ) = ( progress.pid, progress.tag );
+            let report: Box<dyn FnMut( i32 ) + 'a> = Box::new( move |percent| {
+                let _ = env.send( &pid, ( atoms::elxvips_progress(), tag, percent ).encode( env ) );
+            } );
+            Some( report )
+        },
+        None => None
+    }
+}
+
+// same from the async workers, which the VM doesn't manage, the tag is copied into every message
+fn worker_progress_report<'a>( progress: &Option<ProgressOptions<'a>> ) -> Option<Box<dyn FnMut( i32 ) + 'a>> {
+    match progress {
+        Some( progress ) => {
+            let ( pid, tag ) = ( progress.pid, progress.tag );
+            let report: Box<dyn FnMut( i32 ) + 'a> = Box::new( move |percent| {
+                let mut msg_env = OwnedEnv::new();
+                let _ = msg_env.send_and_clear( &pid, |env| ( atoms::elxvips_progress(), tag.in_env( env ), percent ).encode( env ) );
+            } );
+            Some( report )
+        },
+        None => None
+    }
+}
+
+// decodes and prepares the image once, then resizes and encodes it for every variant
+fn encode_variants<'a>( env: Env<'a>, image: Result<VipsImage, LoadError>, pipeline: &Pipeline<'_, 'a>, variants: &[Variant] ) -> Term<'a> {
+    let mut watch = Watch::new( pipeline.deadline, progress_report( env, pipeline.progress ) );
+
+    let guard = pipeline.guard();
+
+    let prepared = match image {
+        Ok( image ) => prepare_image( image, pipeline ).and_then( |prepared| guard.run( &prepared, |prepared| prepared.copy_memory() ) ),
+        Err( LoadError::Limit( limit ) ) => return ( error(), limit ).encode( env ),
+        Err( LoadError::Failed( err ) ) => Err( format!( "failed to open image: {}", err ) )
+    };
+
+    let prepared = match prepared {
+        Ok( prepared ) => prepared,
+        Err( _ ) if watch.expired() => return ( error(), atoms::timeout() ).encode( env ),
+        Err( err ) => return ( error(), err ).encode( env )
+    };
+
+    let results: Vec<Term<'a>> = variants.iter().map( |variant| {
+        let result = finish_image( prepared.shared(), pipeline, &variant.resize, &variant.save )
+            .and_then( |( image, smartcrop )| watch.run( &image, |image| image_into_bytes( image, &variant.save ) ).map( |bytes| ( bytes, smartcrop ) ) );
+
+        match result {
+            Ok( ( bytes, smartcrop ) ) => ( ok(), Binary::from_owned( bytes, env ), smartcrop ).encode( env ),
+            Err( _ ) if watch.expired() => ( error(), atoms::timeout() ).encode( env ),
+            Err( err ) => ( error(), err ).encode( env )
+        }
+    } ).collect();
+
+    ( ok(), results ).encode( env )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_file_variants<'a>(env: Env<'a>, image_input: ImageFile<'a>, variants: Vec<Variant>) -> Result<Term<'a>, Error> {
+    let pipeline = image_input.pipeline();
+    Ok( encode_variants( env, load_from_file( &image_input ), &pipeline, &variants ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_bytes_variants<'a>(env: Env<'a>, image_input: ImageBytes<'a>, variants: Vec<Variant>) -> Result<Term<'a>, Error> {
+    let pipeline = image_input.pipeline();
+    Ok( encode_variants( env, load_from_buffer( &image_input ), &pipeline, &variants ) )
+}
+
+fn encode_handle<'a>( env: Env<'a>, image: Result<VipsImage, LoadError>, pipeline: &Pipeline ) -> Term<'a> {
+    let guard = pipeline.guard();
+    let image = match image {
+        Ok( image ) => guard.run( &image, |image| image.copy_memory() ),
+        Err( LoadError::Limit( limit ) ) => return ( error(), limit ).encode( env ),
+        Err( LoadError::Failed( err ) ) => Err( err )
+    };
+
+    match image {
+        Ok( image ) => ( ok(), ResourceArc::new( image ) ).encode( env ),
+        Err( _ ) if guard.check().is_err() => ( error(), atoms::timeout() ).encode( env ),
+        Err( err ) => ( error(), format!( "failed to load image: {}", err ) ).encode( env )
+    }
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_load_file<'a>(env: Env<'a>, image_input: ImageFile) -> Result<Term<'a>, Error> {
+    let pipeline = image_input.pipeline();
+    Ok( encode_handle( env, load_from_file( &image_input ), &pipeline ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_load_bytes<'a>(env: Env<'a>, image_input: ImageBytes) -> Result<Term<'a>, Error> {
+    let pipeline = image_input.pipeline();
+    Ok( encode_handle( env, load_from_buffer( &image_input ), &pipeline ) )
+}
+
+// a handle is already rotated upright when it was loaded with autorotate
+#[rustler::nif]
+fn vips_get_handle_sizes<'a>(env: Env<'a>, handle: ResourceArc<VipsImage>) -> Result<Term<'a>, Error> {
+    Ok( ( ok(), image_sizes( &handle, false ) ).encode( env ) )
+}
+
+#[rustler::nif]
+fn vips_get_handle_format<'a>(env: Env<'a>, handle: ResourceArc<VipsImage>) -> Result<Term<'a>, Error> {
+    match handle.get_format() {
+        Ok( format ) => Ok( ( ok(), format_to_atom( format ) ).encode( env ) ),
+        Err( err ) => Ok( ( error(), err ).encode( env ) )
+    }
+}
+
+fn encode_trim_box<'a>( env: Env<'a>, image: Result<VipsImage, LoadError>, pipeline: &Pipeline ) -> Term<'a> {
+    let guard = pipeline.guard();
+    let default_trim = TrimOptions {
+        threshold: 10.0,
+        background: Vec::new(),
+    };
+
+    let result = match image {
+        Ok( image ) => trim_box( &image, pipeline.trim.as_ref().unwrap_or( &default_trim ), &guard ),
+        Err( LoadError::Limit( limit ) ) => return ( error(), limit ).encode( env ),
+        Err( LoadError::Failed( err ) ) => Err( format!( "failed to open image: {}", err ) )
+    };
+
+    match result {
+        Ok( trim_box ) => ( ok(), trim_box ).encode( env ),
+        Err( _ ) if guard.check().is_err() => ( error(), atoms::timeout() ).encode( env ),
+        Err( err ) => ( error(), err ).encode( env )
+    }
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_get_file_trim_box<'a>(env: Env<'a>, image_input: ImageFile) -> Result<Term<'a>, Error> {
+    let pipeline = image_input.pipeline();
+    Ok( encode_trim_box( env, load_from_file( &image_input ), &pipeline ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_get_bytes_trim_box<'a>(env: Env<'a>, image_input: ImageBytes) -> Result<Term<'a>, Error> {
+    let pipeline = image_input.pipeline();
+    Ok( encode_trim_box( env, load_from_buffer( &image_input ), &pipeline ) )
+}
+
+// why a processing produced no image
+enum ProcessError {
+    Failed( String ),
+    Limit( LimitExceeded ),
+    Timeout,
+}
+
+impl Encoder for ProcessError {
+    fn encode<'a>( &self, env: Env<'a> ) -> Term<'a> {
+        match self {
+            ProcessError::Failed( err ) => ( error(), err ).encode( env ),
+            ProcessError::Limit( limit ) => ( error(), limit ).encode( env ),
+            ProcessError::Timeout => ( error(), atoms::timeout() ).encode( env ),
+        }
+    }
+}
+
+// loads, transforms and encodes the image within the timeout of the input, reporting its progress
+fn process_image<'a, T, L, F>( env: Env<'a>, pipeline: &Pipeline<'_, 'a>, load: L, encode: F ) -> Result<( T, Option<Smartcrop> ), ProcessError>
+    where L: FnOnce() -> Result<VipsImage, LoadError>, F: FnOnce( &VipsImage ) -> Result<T, String> {
+    let mut watch = Watch::new( pipeline.deadline, progress_report( env, pipeline.progress ) );
+    let result = match load() {
         Ok( image ) => {
-            match resize_image( image, &image_input.resize ) {
-                Ok( image ) => save_image( &image, &image_input.save ),
+            match transform_image( image, pipeline ) {
+                Ok( ( image, smartcrop ) ) => watch.run( &image, encode ).map( |output| ( output, smartcrop ) ),
                 Err( err ) => Err( err )
             }
         },
-        Err( err ) => Err( format!( "failed to open image: {}", err ) )
+        Err( LoadError::Limit( limit ) ) => return Err( ProcessError::Limit( limit ) ),
+        Err( LoadError::Failed( err ) ) => Err( format!( "failed to open image: {}", err ) )
     };
 
     match result {
-        Ok( () ) => Ok( ( ok() ).encode( env ) ),
+        Err( _ ) if watch.expired() => Err( ProcessError::Timeout ),
+        Ok( output ) => Ok( output ),
+        Err( err ) => Err( ProcessError::Failed( err ) )
+    }
+}
+
+// failures other than limits and timeouts are raised when writing a file from a path, as they always were
+fn file_result<'a>( env: Env<'a>, result: Result<Term<'a>, ProcessError> ) -> NifResult<Term<'a>> {
+    match result {
+        Ok( term ) => Ok( term ),
+        Err( ProcessError::Failed( err ) ) => Err( Error::Term( Box::new( err ) ) ),
+        Err( err ) => Ok( err.encode( env ) )
+    }
+}
+
+fn encoded<'a, T: Encoder>( env: Env<'a>, result: Result<T, ProcessError> ) -> Result<Term<'a>, Error> {
+    match result {
+        Ok( output ) => Ok( output.encode( env ) ),
+        Err( err ) => Ok( err.encode( env ) )
+    }
+}
+
+// decoding and encoding take far longer than a scheduler slice, processing runs on dirty CPU schedulers,
+// writing the result to a file included, metadata probes only read headers and stay on normal schedulers
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_file_to_file<'a>(env: Env<'a>, image_input: ImageFile<'a>) -> NifResult<Term<'a>> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_file( &image_input ), |image| save_image( image, &image_input.save ) );
+    file_result( env, result.map( |_| ok().encode( env ) ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_file_to_bytes<'a>(env: Env<'a>, image_input: ImageFile<'a>) -> Result<Term<'a>, Error> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_file( &image_input ), |image| image_into_bytes( image, &image_input.save ) );
+    encoded( env, result.map( |( bytes, _ )| ( ok(), Binary::from_owned( bytes, env ) ) ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_bytes_to_bytes<'a>(env: Env<'a>, image_input: ImageBytes<'a>) -> Result<Term<'a>, Error> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_buffer( &image_input ), |image| image_into_bytes( image, &image_input.save ) );
+    encoded( env, result.map( |( bytes, _ )| ( ok(), Binary::from_owned( bytes, env ) ) ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_bytes_to_file<'a>(env: Env<'a>, image_input: ImageBytes<'a>) -> Result<Term<'a>, Error> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_buffer( &image_input ), |image| save_image( image, &image_input.save ) );
+    encoded( env, result.map( |_| ok() ) )
+}
+
+// same as the NIFs above, with the smart crop that was made, if any, added to the result
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_file_to_file_smartcrop<'a>(env: Env<'a>, image_input: ImageFile<'a>) -> NifResult<Term<'a>> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_file( &image_input ), |image| save_image( image, &image_input.save ) );
+    file_result( env, result.map( |( _, smartcrop )| ( ok(), smartcrop ).encode( env ) ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_file_to_bytes_smartcrop<'a>(env: Env<'a>, image_input: ImageFile<'a>) -> Result<Term<'a>, Error> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_file( &image_input ), |image| image_into_bytes( image, &image_input.save ) );
+    encoded( env, result.map( |( bytes, smartcrop )| ( ok(), Binary::from_owned( bytes, env ), smartcrop ) ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_bytes_to_bytes_smartcrop<'a>(env: Env<'a>, image_input: ImageBytes<'a>) -> Result<Term<'a>, Error> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_buffer( &image_input ), |image| image_into_bytes( image, &image_input.save ) );
+    encoded( env, result.map( |( bytes, smartcrop )| ( ok(), Binary::from_owned( bytes, env ), smartcrop ) ) )
+}
+
+#[rustler::nif(schedule = "DirtyCpu")]
+fn vips_process_bytes_to_file_smartcrop<'a>(env: Env<'a>, image_input: ImageBytes<'a>) -> Result<Term<'a>, Error> {
+    let result = process_image( env, &image_input.pipeline(), || load_from_buffer( &image_input ), |image| save_image( image, &image_input.save ) );
+    encoded( env, result.map( |( _, smartcrop )| ( ok(), smartcrop ) ) )
+}
+
+// threads of a pool, from the environment variable or the number of cores
+fn pool_size( name: &str ) -> usize {
+    match env::var( name ) {
+        Ok( var ) => match var.parse::<usize>() {
+            Ok( num ) => num,
+            Err( _ ) => {
+                panic!( "Couldn't convert {}={:?} to int", name, var )
+            }
+        },
+        Err(_) => num_cpus::get()
+    }
+}
+
+// processing queued by the async NIFs, VIPS_ASYNC_WORKERS sets the number of threads
+static ASYNC_POOL: LazyLock<Result<WorkerPool, String>> = LazyLock::new( || WorkerPool::new( pool_size( "VIPS_ASYNC_WORKERS" ), "lvips-async" ) );
+
+// shared by every batch, VIPS_BATCH_WORKERS caps the threads of all batches running at once
+static BATCH_POOL: LazyLock<Result<WorkerPool, String>> = LazyLock::new( || WorkerPool::new( pool_size( "VIPS_BATCH_WORKERS" ), "lvips-batch" ) );
+
+// streams running at once, VIPS_STREAM_WORKERS sets the number of threads, the streams started beyond it wait for one
+static STREAM_POOL: LazyLock<Result<WorkerPool, String>> = LazyLock::new( || WorkerPool::new( pool_size( "VIPS_STREAM_WORKERS" ), "lvips-stream" ) );
+
+// a pool that failed to start fails every task queued on it
+fn queue_task<F>( pool: &Result<WorkerPool, String>, task: F ) -> Result<(), String> where F: FnOnce() + Send + 'static {
+    match pool {
+        Ok( pool ) => pool.execute( task ),
+        Err( err ) => Err( err.to_owned() )
+    }
+}
+
+// an async job, the term Elixir holds is also the reference tagging the result message
+struct Job {
+    cancelled: AtomicBool,
+    // set when timeout_ms of the image ran out
+    timed_out: AtomicBool,
+    // load limit the input exceeded
+    limit: Mutex<Option<LimitExceeded>>,
+    // private copies of the loaded image and of the reloads, every step computes from one of them,
+    // they are killed on cancel so libvips stops computing the transform and the encoding alike
+    images: Mutex<Vec<VipsImage>>,
+    // set by vips_cancel and vips_stream_cancel, the caller flushes its mailbox then and expects no message afterwards
+    silenced: Mutex<bool>,
+}
+
+impl Job {
+    fn new() -> Job {
+        Job {
+            cancelled: AtomicBool::new( false ),
+            timed_out: AtomicBool::new( false ),
+            limit: Mutex::new( None ),
+            images: Mutex::new( Vec::new() ),
+            silenced: Mutex::new( false ),
+        }
+    }
+
+    fn cancel( &self ) {
+        let images = self.images.lock().unwrap();
+        self.cancelled.store( true, Ordering::SeqCst );
+        for image in images.iter() {
+            image.set_kill( true );
+        }
+    }
+
+    fn is_cancelled( &self ) -> bool {
+        self.cancelled.load( Ordering::SeqCst )
+    }
+
+    fn is_timed_out( &self ) -> bool {
+        self.timed_out.load( Ordering::SeqCst )
+    }
+
+    // keeps a limit violation to report, the failure still travels as a message
+    fn exceeded( &self, err: LoadError ) -> String {
+        let message = err.message();
+        if let LoadError::Limit( limit ) = err {
+            *self.limit.lock().unwrap() = Some( limit );
+        }
+        message
+    }
+
+    // why the job failed when it is more than a message: cancelled, timed out or over a load limit
+    fn failure<'a>( &self, env: Env<'a> ) -> Option<Term<'a>> {
+        if self.is_cancelled() {
+            return Some( atoms::cancelled().encode( env ) );
+        }
+        if self.is_timed_out() {
+            return Some( atoms::timeout().encode( env ) );
+        }
+        self.limit.lock().unwrap().as_ref().map( |limit| limit.encode( env ) )
+    }
+
+    // the copy to compute from, killing it leaves the image untouched for the handles and jobs sharing it,
+    // fails when the job was cancelled before the step could start
+    fn track( &self, image: &VipsImage ) -> Result<VipsImage, String> {
+        let image = image.copy()?;
+        let mut tracked = self.images.lock().unwrap();
+        if self.is_cancelled() {
+            return Err( "cancelled".to_string() );
+        }
+        tracked.push( image.shared() );
+        Ok( image )
+    }
+
+    // once the job is done, on success and failure alike
+    fn untrack( &self ) {
+        for image in self.images.lock().unwrap().drain( .. ) {
+            image.set_kill( false );
+        }
+    }
+}
+
+// the caller is monitored, there is nobody left to send the result to once it is down
+#[rustler::resource_impl]
+impl rustler::Resource for Job {
+    fn down<'a>( &'a self, _env: Env<'a>, _pid: LocalPid, _monitor: Monitor ) {
+        self.cancel();
+    }
+}
+
+enum JobOutput {
+    Bytes( OwnedBinary ),
+    File( String ),
+}
+
+fn process_job( job: &Job, image: Result<VipsImage, LoadError>, pipeline: &Pipeline, to: Atom ) -> Result<JobOutput, String> {
+    let mut watch = Watch::new( pipeline.deadline, worker_progress_report( pipeline.progress ) );
+    let image = match image {
+        Ok( image ) => image,
+        Err( err ) => return Err( format!( "failed to open image: {}", job.exceeded( err ) ) )
+    };
+
+    // the encoding computes from the images tracked by the transform, cancelling kills it as well
+    let result = transform_image( image, pipeline ).and_then( |( image, _ )| match to {
+        to if to == atoms::file() => watch.run( &image, |image| save_image( image, pipeline.save ) ).map( |_| JobOutput::File( pipeline.save.path.to_owned() ) ),
+        _ => watch.run( &image, |image| image_into_bytes( image, pipeline.save ) ).map( JobOutput::Bytes ),
+    } );
+    job.untrack();
+
+    job.timed_out.store( result.is_err() && watch.expired(), Ordering::SeqCst );
+    result
+}
+
+// {:ok, bytes | path} or {:error, reason} of a finished job
+fn job_reply<'a>( env: Env<'a>, job: &Job, result: Result<JobOutput, String> ) -> Term<'a> {
+    match ( job.failure( env ), result ) {
+        ( Some( failure ), _ ) => ( error(), failure ).encode( env ),
+        ( None, Ok( JobOutput::Bytes( bytes ) ) ) => ( ok(), Binary::from_owned( bytes, env ) ).encode( env ),
+        ( None, Ok( JobOutput::File( path ) ) ) => ( ok(), path ).encode( env ),
+        ( None, Err( err ) ) => ( error(), err ).encode( env ),
+    }
+}
+
+fn run_job( job: &Job, image_input: Term, to: Atom ) -> Result<JobOutput, String> {
+    if job.is_cancelled() {
+        return Err( "cancelled".to_string() );
+    }
+
+    if let Ok( image_input ) = image_input.decode::<ImageFile>() {
+        let mut pipeline = image_input.pipeline();
+        pipeline.job = Some( job );
+        process_job( job, load_from_file( &image_input ), &pipeline, to )
+    } else if let Ok( image_input ) = image_input.decode::<ImageBytes>() {
+        let mut pipeline = image_input.pipeline();
+        pipeline.job = Some( job );
+        process_job( job, load_from_buffer( &image_input ), &pipeline, to )
+    } else {
+        Err( "expected %Elxvips.ImageFile{} or %Elxvips.ImageBytes{}".to_string() )
+    }
+}
+
+// queues the processing and returns the job right away, the caller then receives {job, {:ok, bytes | path}}
+// or {job, {:error, reason}}, the job is cancelled when the caller goes down
+#[rustler::nif]
+fn vips_process_async<'a>(env: Env<'a>, image_input: Term<'a>, to: Atom) -> Result<Term<'a>, Error> {
+    if to != atoms::bytes() && to != atoms::file() {
+        return Ok( ( error(), "target must be :bytes or :file" ).encode( env ) );
+    }
+
+    let job = ResourceArc::new( Job::new() );
+
+    let pid = env.pid();
+    env.monitor( &job, &pid );
+
+    let input_env = OwnedEnv::new();
+    let saved_input = input_env.save( image_input );
+    let worker_job = job.clone();
+
+    let queued = queue_task( &ASYNC_POOL, move || {
+        let result = input_env.run( |env| run_job( &worker_job, saved_input.load( env ), to ) );
+
+        let silenced = worker_job.silenced.lock().unwrap();
+        if !*silenced {
+            let mut msg_env = OwnedEnv::new();
+            let _ = msg_env.send_and_clear( &pid, |env| ( worker_job.clone(), job_reply( env, &worker_job, result ) ).encode( env ) );
+        }
+    } );
+
+    match queued {
+        Ok( () ) => Ok( ( ok(), job ).encode( env ) ),
         Err( err ) => Ok( ( error(), err ).encode( env ) )
     }
 }
 
+// stops a running job, no result is sent once this returns, one sent before is left in the caller's mailbox
+#[rustler::nif]
+fn vips_cancel( job: ResourceArc<Job> ) -> Atom {
+    *job.silenced.lock().unwrap() = true;
+    job.cancel();
+    ok()
+}
+
+// independent jobs processed together, the results are kept in input order until the last job is done
+struct Batch {
+    jobs: Vec<Job>,
+    results: Mutex<Vec<Option<Result<JobOutput, String>>>>,
+    remaining: AtomicUsize,
+    // set by vips_cancel_batch, the caller stops waiting then and expects no results afterwards
+    silenced: Mutex<bool>,
+}
+
+impl Batch {
+    // jobs already done keep their result
+    fn cancel( &self ) {
+        let results = self.results.lock().unwrap();
+        for ( job, result ) in self.jobs.iter().zip( results.iter() ) {
+            if result.is_none() {
+                job.cancel();
+            }
+        }
+    }
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for Batch {
+    fn down<'a>( &'a self, _env: Env<'a>, _pid: LocalPid, _monitor: Monitor ) {
+        self.cancel();
+    }
+}
+
+fn batch_reply<'a>( env: Env<'a>, batch: &Batch ) -> Term<'a> {
+    let mut results = batch.results.lock().unwrap();
+    let replies: Vec<Term<'a>> = batch.jobs.iter()
+        .zip( results.drain( .. ) )
+        .map( |( job, result )| job_reply( env, job, result.unwrap_or_else( || Err( "cancelled".to_string() ) ) ) )
+        .collect();
+    ( ok(), replies ).encode( env )
+}
+
+// jobs of a batch not started yet, with their index, input and target
+type BatchQueue = Mutex<VecDeque<( usize, OwnedEnv, SavedTerm, Atom )>>;
+
+// one of the width tasks of a batch, taking jobs in input order until none is left
+fn run_batch_lane( batch: &ResourceArc<Batch>, queue: &BatchQueue, pid: LocalPid ) {
+    loop {
+        let next = queue.lock().unwrap().pop_front();
+        let ( index, input_env, saved_input, to ) = match next {
+            Some( next ) => next,
+            None => return
+        };
+
+        let job = &batch.jobs[ index ];
+        let result = input_env.run( |env| run_job( job, saved_input.load( env ), to ) );
+        batch.results.lock().unwrap()[ index ] = Some( result );
+
+        // the last job to finish sends every result
+        if batch.remaining.fetch_sub( 1, Ordering::SeqCst ) == 1 {
+            let silenced = batch.silenced.lock().unwrap();
+            if !*silenced {
+                let mut msg_env = OwnedEnv::new();
+                let _ = msg_env.send_and_clear( &pid, |env| ( batch.clone(), batch_reply( env, batch ) ).encode( env ) );
+            }
+        }
+    }
+}
+
+// runs the jobs, each an image with its target :bytes or :file, at most width at once on the threads shared by
+// every batch, the caller then receives {batch, {:ok, results}} with {:ok, bytes | path}
+// or {:error, reason} per job in input order, the batch is cancelled when the caller goes down
+#[rustler::nif]
+fn vips_process_batch<'a>(env: Env<'a>, image_inputs: Vec<( Term<'a>, Atom )>, width: usize) -> Result<Term<'a>, Error> {
+    if image_inputs.iter().any( |( _, to )| *to != atoms::bytes() && *to != atoms::file() ) {
+        return Ok( ( error(), "target must be :bytes or :file" ).encode( env ) );
+    }
+
+    let batch = ResourceArc::new( Batch {
+        jobs: image_inputs.iter().map( |_| Job::new() ).collect(),
+        results: Mutex::new( image_inputs.iter().map( |_| None ).collect() ),
+        remaining: AtomicUsize::new( image_inputs.len() ),
+        silenced: Mutex::new( false ),
+    } );
+
+    let pid = env.pid();
+    env.monitor( &batch, &pid );
+
+    if image_inputs.is_empty() {
+        let _ = env.send( &pid, ( batch.clone(), batch_reply( env, &batch ) ).encode( env ) );
+        return Ok( ( ok(), batch ).encode( env ) );
+    }
+
+    let lanes = width.max( 1 ).min( image_inputs.len() );
+    let queue: Arc<BatchQueue> = Arc::new( Mutex::new( image_inputs.into_iter().enumerate().map( |( index, ( image_input, to ) )| {
+        let input_env = OwnedEnv::new();
+        let saved_input = input_env.save( image_input );
+        ( index, input_env, saved_input, to )
+    } ).collect() ) );
+
+    for lane in 0..lanes {
+        let ( lane_batch, lane_queue ) = ( batch.clone(), queue.clone() );
+        match queue_task( &BATCH_POOL, move || run_batch_lane( &lane_batch, &lane_queue, pid ) ) {
+            Ok( () ) => (),
+            Err( err ) if lane == 0 => return Ok( ( error(), err ).encode( env ) ),
+            // the lanes already queued work through every job
+            Err( _ ) => break
+        }
+    }
+
+    Ok( ( ok(), batch ).encode( env ) )
+}
+
+// stops every job of a batch not done yet, no results are sent once this returns
+#[rustler::nif]
+fn vips_cancel_batch( batch: ResourceArc<Batch> ) -> Atom {
+    *batch.silenced.lock().unwrap() = true;
+    batch.cancel();
+    ok()
+}
+
+// a streaming job, input chunks are requested from the caller and encoded chunks are sent back
+// as soon as libvips produces them, so neither the source nor the output is ever held in full
+struct ImageStream {
+    job: Job,
+    // None marks the end of the input
+    input: Mutex<mpsc::Sender<Option<Vec<u8>>>>,
+}
+
+impl ImageStream {
+    // the caller goes on without the stream, nothing is sent to it anymore
+    fn stop( &self ) {
+        *self.job.silenced.lock().unwrap() = true;
+        self.cancel();
+    }
+
+    fn cancel( &self ) {
+        self.job.cancel();
+        // wakes up a loader waiting for the next chunk
+        let _ = self.input.lock().unwrap().send( None );
+    }
+}
+
+#[rustler::resource_impl]
+impl rustler::Resource for ImageStream {
+    fn down<'a>( &'a self, _env: Env<'a>, _pid: LocalPid, _monitor: Monitor ) {
+        self.cancel();
+    }
+}
+
+fn notify_stream<F>( pid: &LocalPid, stream: &ResourceArc<ImageStream>, message: F ) where F: for<'a> FnOnce( Env<'a> ) -> Term<'a> {
+    let silenced = stream.job.silenced.lock().unwrap();
+    if !*silenced {
+        let mut msg_env = OwnedEnv::new();
+        let _ = msg_env.send_and_clear( pid, |env| ( stream.clone(), message( env ) ).encode( env ) );
+    }
+}
+
+// asks the caller for a chunk with {stream, :more} whenever the loader has consumed the previous one
+// with max_bytes set, reading past it fails the load as a limit violation, waiting past the deadline fails it too
+fn stream_reader( stream: ResourceArc<ImageStream>, pid: LocalPid, receiver: mpsc::Receiver<Option<Vec<u8>>>, max_bytes: Option<u64>, deadline: Option<Instant> ) -> ReadFn {
+    let mut chunk: Vec<u8> = Vec::new();
+    let mut offset = 0;
+    let mut eof = false;
+    let mut total: u64 = 0;
+
+    Box::new( move |buffer: &mut [u8]| {
+        while offset >= chunk.len() {
+            if stream.job.is_cancelled() {
+                return -1;
+            }
+            if eof {
+                return 0;
+            }
+
+            notify_stream( &pid, &stream, |env| atoms::more().encode( env ) );
+            let next = match deadline {
+                Some( deadline ) => match receiver.recv_timeout( deadline.saturating_duration_since( Instant::now() ) ) {
+                    Ok( next ) => next,
+                    Err( mpsc::RecvTimeoutError::Timeout ) => return -1,
+                    Err( mpsc::RecvTimeoutError::Disconnected ) => None
+                },
+                None => receiver.recv().unwrap_or( None )
+            };
+            match next {
+                Some( next ) => {
+                    total += next.len() as u64;
+                    chunk = next;
+                    offset = 0;
+                },
+                None => eof = true
+            }
+
+            if let Err( err ) = check_limit( "max_bytes", total, max_bytes ) {
+                stream.job.exceeded( err );
+                return -1;
+            }
+        }
+
+        let length = buffer.len().min( chunk.len() - offset );
+        buffer[ ..length ].copy_from_slice( &chunk[ offset..offset + length ] );
+        offset += length;
+        length as i64
+    } )
+}
+
+fn stream_writer( stream: ResourceArc<ImageStream>, pid: LocalPid ) -> WriteFn {
+    Box::new( move |bytes: &[u8]| {
+        if stream.job.is_cancelled() {
+            return -1;
+        }
+        match into_binary( bytes ) {
+            Ok( binary ) => {
+                notify_stream( &pid, &stream, |env| ( atoms::chunk(), Binary::from_owned( binary, env ) ).encode( env ) );
+                bytes.len() as i64
+            },
+            // the caller stops at the error, the encoding fails with the write
+            Err( err ) => {
+                notify_stream( &pid, &stream, |env| ( error(), err ).encode( env ) );
+                -1
+            }
+        }
+    } )
+}
+
+fn stream_job( stream: &ResourceArc<ImageStream>, pid: LocalPid, image: Result<VipsImage, LoadError>, pipeline: &Pipeline ) -> Result<(), String> {
+    let mut watch = Watch::new( pipeline.deadline, worker_progress_report( pipeline.progress ) );
+    // a pulled input may fail to load because the caller didn't send it in time
+    let result = match image {
+        Ok( image ) => transform_image( image, pipeline ).and_then( |( image, _ )| {
+            VipsTarget::new( stream_writer( stream.clone(), pid ) )
+                .and_then( |target| watch.run( &image, |image| image_into_target( image, pipeline.save, &target ) ) )
+        } ),
+        Err( err ) => Err( format!( "failed to open image: {}", stream.job.exceeded( err ) ) )
+    };
+    stream.job.untrack();
+
+    stream.job.timed_out.store( result.is_err() && watch.expired(), Ordering::SeqCst );
+    result
+}
+
+fn load_from_stream( stream: &ResourceArc<ImageStream>, pid: LocalPid, receiver: mpsc::Receiver<Option<Vec<u8>>>, image_input: &ImageBytes, deadline: Option<Instant> ) -> Result<VipsImage, LoadError> {
+    let max_bytes = image_input.limits.as_ref().and_then( |limits| limits.max_bytes );
+    let options = load_options( &image_input.limits )?;
+
+    // the image holds its own reference to the source
+    let source = match VipsSource::new( stream_reader( stream.clone(), pid, receiver, max_bytes, deadline ) ) {
+        Ok( source ) => source,
+        Err( err ) => return Err( LoadError::Failed( err ) )
+    };
+    limit_image( VipsImage::from_source( &source, &options ), &image_input.limits, image_input.autorotate )
+}
+
+fn run_stream( stream: &ResourceArc<ImageStream>, pid: LocalPid, receiver: mpsc::Receiver<Option<Vec<u8>>>, image_input: Term, pull: bool ) -> Result<(), String> {
+    if stream.job.is_cancelled() {
+        return Err( "cancelled".to_string() );
+    }
+
+    if let Ok( image_input ) = image_input.decode::<ImageFile>() {
+        let mut pipeline = image_input.pipeline();
+        pipeline.job = Some( &stream.job );
+        stream_job( stream, pid, load_from_file( &image_input ), &pipeline )
+    } else if let Ok( image_input ) = image_input.decode::<ImageBytes>() {
+        let mut pipeline = image_input.pipeline();
+        pipeline.job = Some( &stream.job );
+        let image = match pull {
+            true => load_from_stream( stream, pid, receiver, &image_input, pipeline.deadline ),
+            false => load_from_buffer( &image_input )
+        };
+        stream_job( stream, pid, image, &pipeline )
+    } else {
+        Err( "expected %Elxvips.ImageFile{} or %Elxvips.ImageBytes{}".to_string() )
+    }
+}
+
+// processes the image on the stream workers, streaming the encoded output as {stream, {:chunk, bytes}}
+// followed by {stream, :done} or {stream, {:error, reason}}, with pull set the input is read from
+// the caller as well: every {stream, :more} is answered with vips_stream_write or vips_stream_close,
+// a stream mostly waits on the caller, so streams have their own pool rather than holding the async workers
+#[rustler::nif]
+fn vips_stream_process<'a>(env: Env<'a>, image_input: Term<'a>, pull: bool) -> Term<'a> {
+    let ( sender, receiver ) = mpsc::channel();
+    let stream = ResourceArc::new( ImageStream {
+        job: Job::new(),
+        input: Mutex::new( sender ),
+    } );
+
+    let pid = env.pid();
+    env.monitor( &stream, &pid );
+
+    let input_env = OwnedEnv::new();
+    let saved_input = input_env.save( image_input );
+    let worker_stream = stream.clone();
+
+    let queued = queue_task( &STREAM_POOL, move || {
+        let result = input_env.run( |env| run_stream( &worker_stream, pid, receiver, saved_input.load( env ), pull ) );
+
+        notify_stream( &pid, &worker_stream, |env| match ( worker_stream.job.failure( env ), result ) {
+            ( Some( failure ), _ ) => ( error(), failure ).encode( env ),
+            ( None, Ok( () ) ) => atoms::done().encode( env ),
+            ( None, Err( err ) ) => ( error(), err ).encode( env ),
+        } );
+    } );
+
+    match queued {
+        Ok( () ) => ( ok(), stream ).encode( env ),
+        Err( err ) => ( error(), err ).encode( env )
+    }
+}
+
+#[rustler::nif]
+fn vips_stream_write( stream: ResourceArc<ImageStream>, chunk: Binary ) -> Atom {
+    let _ = stream.input.lock().unwrap().send( Some( chunk.as_slice().to_vec() ) );
+    ok()
+}
+
+#[rustler::nif]
+fn vips_stream_close( stream: ResourceArc<ImageStream> ) -> Atom {
+    let _ = stream.input.lock().unwrap().send( None );
+    ok()
+}
+
+// stops a stream, no message is sent once this returns, those sent before are left in the caller's mailbox
+#[rustler::nif]
+fn vips_stream_cancel( stream: ResourceArc<ImageStream> ) -> Atom {
+    stream.stop();
+    ok()
+}
+
 rustler::init!("Elixir.Elxvips.Native", load=on_load );
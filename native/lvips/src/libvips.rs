@@ -3,14 +3,17 @@ pub mod bindings;
 pub mod utils;
 pub mod save_options;
 pub mod globals;
+pub mod stream;
 use std::ffi::{CStr, c_void};
-use utils::{c_string, null};
+use utils::{c_string, null, VipsBuffer, VipsDoubleArray};
 use std::ffi::{CString};
-use save_options::{JpegSaveOptions, PngSaveOptions, WebPSaveOptions, SmartcropOptions};
+use save_options::{JpegSaveOptions, PngSaveOptions, WebPSaveOptions, SmartcropOptions, ResizeOptions, GravityOptions, CompassDirection, ThumbnailOptions};
 use base64::{engine::general_purpose, Engine as _};
 use std::fs;
+use std::sync::Arc;
+use stream::{VipsSource, VipsTarget};
 
-use self::save_options::HeifsaveOptions;
+use self::save_options::{LoadOptions, HeifsaveOptions, RotateOptions, Angle, Direction, FindTrimOptions, SmartcropResult, Interpolate, AffineOptions, MapimOptions, BlendMode};
 
 #[derive(PartialEq)]
 pub enum VipsFormat {
@@ -38,6 +41,124 @@ pub fn concurrency_set( max: i32 ) {
     }
 }
 
+pub fn cache_set_max( max: i32 ) {
+    unsafe {
+        bindings::vips_cache_set_max( max );
+    }
+}
+
+pub fn cache_set_max_mem( max_mem: usize ) {
+    unsafe {
+        bindings::vips_cache_set_max_mem( max_mem );
+    }
+}
+
+pub fn cache_set_max_files( max_files: i32 ) {
+    unsafe {
+        bindings::vips_cache_set_max_files( max_files );
+    }
+}
+
+// releases every cached operation, images still referenced stay alive
+pub fn cache_drop_all() {
+    unsafe {
+        bindings::vips_cache_drop_all();
+    }
+}
+
+pub struct CacheStats {
+    pub size: i32,
+    pub max: i32,
+    pub max_mem: usize,
+    pub max_files: i32,
+}
+
+pub fn cache_stats() -> CacheStats {
+    unsafe {
+        CacheStats {
+            size: bindings::vips_cache_get_size(),
+            max: bindings::vips_cache_get_max(),
+            max_mem: bindings::vips_cache_get_max_mem(),
+            max_files: bindings::vips_cache_get_max_files(),
+        }
+    }
+}
+
+// memory and files libvips is tracking right now, pixel buffers included
+pub struct TrackedStats {
+    pub mem: usize,
+    pub mem_highwater: usize,
+    pub allocs: i32,
+    pub files: i32,
+}
+
+pub fn tracked_stats() -> TrackedStats {
+    unsafe {
+        TrackedStats {
+            mem: bindings::vips_tracked_get_mem(),
+            mem_highwater: bindings::vips_tracked_get_mem_highwater(),
+            allocs: bindings::vips_tracked_get_allocs(),
+            files: bindings::vips_tracked_get_files(),
+        }
+    }
+}
+
+// blocks or unblocks every operation flagged as untrusted, such as the ImageMagick, matload and FITS loaders
+pub fn block_untrusted_set( state: bool ) {
+    unsafe {
+        bindings::vips_block_untrusted_set( state as i32 );
+    }
+}
+
+// name is a nickname such as "magickload" or a class name such as "VipsForeignLoadMagick"
+pub fn operation_exists( name: &str ) -> bool {
+    let base = c_string( "VipsOperation" ).unwrap();
+    match c_string( name ) {
+        Ok( name ) => unsafe { bindings::vips_type_find( base.as_ptr(), name.as_ptr() ) != 0 },
+        Err( _ ) => false
+    }
+}
+
+// the operation and all its subclasses are blocked or unblocked
+pub fn operation_block_set( name: &str, state: bool ) -> Result<(), String> {
+    if !operation_exists( name ) {
+        return Err( format!( "operation {} not found", name ) );
+    }
+
+    let name = c_string( name ).unwrap();
+    unsafe {
+        bindings::vips_operation_block_set( name.as_ptr(), state as i32 );
+    }
+    Ok( () )
+}
+
+unsafe extern "C" fn collect_allowed_loader( class: *mut c_void, loaders: *mut c_void, _b: *mut c_void ) -> *mut c_void {
+    let loaders = unsafe { &mut *( loaders as *mut Vec<String> ) };
+    let operation_class = class as *mut bindings::VipsOperationClass;
+    unsafe {
+        if ( *operation_class ).flags & bindings::VipsOperationFlags_VIPS_OPERATION_BLOCKED == 0 {
+            let nickname = CStr::from_ptr( ( *operation_class ).parent_class.nickname );
+            loaders.push( nickname.to_string_lossy().into_owned() );
+        }
+    }
+    null()
+}
+
+// nicknames of the loaders that are not blocked, untrusted loaders included unless blocked
+pub fn allowed_loaders() -> Vec<String> {
+    let base = c_string( "VipsForeignLoad" ).unwrap();
+    let mut loaders: Vec<String> = Vec::new();
+    unsafe {
+        bindings::vips_foreign_map(
+            base.as_ptr(),
+            Some( collect_allowed_loader ),
+            &mut loaders as *mut Vec<String> as *mut c_void,
+            null()
+        );
+    }
+    loaders
+}
+
 pub fn vips_init() {
     unsafe {
         bindings::vips_init( CString::new( "lvips" ).unwrap().as_ptr() );
@@ -47,15 +168,57 @@ pub fn vips_init() {
 #[derive(Clone)]
 enum ImageSource {
     File( String ),
-    Buffer( Vec<u8> ),
+    // shared with every derived image, libvips may still read from it lazily
+    Buffer( Arc<[u8]> ),
     None,
 }
 
+// the operation keeps its own reference, the caller drops this one once the operation is built
+fn new_interpolate( interpolate: Interpolate ) -> Result<*mut bindings::VipsInterpolate, String> {
+    let nickname = c_string( interpolate.nickname() ).unwrap();
+    unsafe {
+        let interpolator = bindings::vips_interpolate_new( nickname.as_ptr() );
+        match interpolator.is_null() {
+            true => Err( error_buffer() ),
+            false => Ok( interpolator )
+        }
+    }
+}
+
+struct EvalWatch<'e> {
+    eval: &'e mut dyn FnMut( i32 ) -> bool,
+    killed: bool,
+}
+
+// eval is emitted from the thread running the computation, the watch outlives the handler
+unsafe extern "C" fn eval_handler( image: *mut bindings::VipsImage, progress: *mut bindings::VipsProgress, data: *mut c_void ) {
+    let watch = unsafe { &mut *( data as *mut EvalWatch ) };
+    let percent = unsafe { ( *progress ).percent };
+
+    if !watch.killed && !( watch.eval )( percent ) {
+        watch.killed = true;
+        unsafe {
+            bindings::vips_image_set_kill( image, 1 );
+        }
+    }
+}
+
 pub struct VipsImage {
     image: *mut bindings::_VipsImage,
     source: ImageSource,
+    // true while the image is exactly what the loader produced, so it can be reloaded with shrink-on-load
+    pristine: bool,
+    // orientation tag was applied to the pixels, reloading must rotate as well
+    autorotated: bool,
 }
 
+// the pixels and metadata of an image are never modified once built and its reference count is atomic,
+// so it can be shared between the threads holding a handle, what does change an image, set_kill and the
+// signal connected by watch_eval, is only used on a private copy() made by the processing that watches it,
+// other threads reach that copy only to set its kill flag, which libvips reads while computing
+unsafe impl Send for VipsImage {}
+unsafe impl Sync for VipsImage {}
+
 impl VipsImage {
     pub fn get_width( &self ) -> i32 {
         unsafe {
@@ -72,6 +235,38 @@ impl VipsImage {
             bindings::vips_image_get_height( self.image )
         }
     }
+    pub fn get_bands( &self ) -> i32 {
+        unsafe {
+            bindings::vips_image_get_bands( self.image )
+        }
+    }
+    /// pages in the file, 1 for formats without pages
+    pub fn get_pages( &self ) -> i32 {
+        unsafe {
+            bindings::vips_image_get_n_pages( self.image )
+        }
+    }
+    pub fn get_interpretation( &self ) -> i32 {
+        unsafe {
+            bindings::vips_image_guess_interpretation( self.image ) as i32
+        }
+    }
+    pub fn has_alpha( &self ) -> bool {
+        unsafe {
+            bindings::vips_image_hasalpha( self.image ) != 0
+        }
+    }
+    pub fn is_pristine( &self ) -> bool {
+        self.pristine
+    }
+    pub fn is_autorotated( &self ) -> bool {
+        self.autorotated
+    }
+    pub fn orientation_swap( &self ) -> bool {
+        unsafe {
+            bindings::vips_image_get_orientation_swap( self.image ) != 0
+        }
+    }
     pub fn get_string( &self, vips_string: &CStr ) -> Result<String, String> {
         unsafe {
             let params = globals::get_params().unwrap();
@@ -93,11 +288,11 @@ impl VipsImage {
         let params = globals::get_params().unwrap();
         let format_string: &str = &self.get_string( &params.vips_loader ).unwrap();
         match format_string {
-            "jpegload"  | "jpegload_buffer" => Ok( VipsFormat::JPEG ),
-            "pngload"   | "pngload_buffer" => Ok( VipsFormat::PNG ),
-            "webpload"   | "webpload_buffer" => Ok( VipsFormat::WEBP ),
-            "heifload"  | "heifload_buffer" => Ok( VipsFormat::AVIF ),
-            "svgload"   | "svgload_buffer" => Ok( VipsFormat::SVG ),
+            "jpegload"  | "jpegload_buffer" | "jpegload_source" => Ok( VipsFormat::JPEG ),
+            "pngload"   | "pngload_buffer" | "pngload_source" => Ok( VipsFormat::PNG ),
+            "webpload"   | "webpload_buffer" | "webpload_source" => Ok( VipsFormat::WEBP ),
+            "heifload"  | "heifload_buffer" | "heifload_source" => Ok( VipsFormat::AVIF ),
+            "svgload"   | "svgload_buffer" | "svgload_source" => Ok( VipsFormat::SVG ),
             _ => Err( "unknown format".to_string() )
         }
     }
@@ -113,12 +308,40 @@ impl VipsImage {
                 Ok( VipsImage{
                     image: image,
                     source: ImageSource::File( path.to_string() ),
+                    pristine: true,
+                    autorotated: false,
+                } )
+            }
+        }
+    }
+
+    // only the header is read, pixels are decoded when the image is computed
+    pub fn from_file_opts( path: &str, options: &LoadOptions ) -> Result<VipsImage, String> {
+        let filename = c_string( path ).unwrap();
+        let params = globals::get_params().unwrap();
+        unsafe {
+
+            let image = bindings::vips_image_new_from_file(
+                filename.as_ptr(),
+                params.fail_on.as_ptr(),            options.fail_on as i32,
+                utils::NULL
+            );
+
+            if image.is_null() {
+                Err( error_buffer() )
+            } else {
+                Ok( VipsImage{
+                    image: image,
+                    source: ImageSource::File( path.to_string() ),
+                    // reloading for shrink-on-load would not fail on anything
+                    pristine: true,
+                    autorotated: false,
                 } )
             }
         }
     }
 
-    pub fn from_pdf_file( path: &str, page: &i32, n: &i32 ) -> Result<VipsImage, String> {
+    pub fn from_pdf_file( path: &str, page: &i32, n: &i32, options: &LoadOptions ) -> Result<VipsImage, String> {
         let filename = c_string( path ).unwrap();
         let params = globals::get_params().unwrap();
 
@@ -131,11 +354,14 @@ impl VipsImage {
                 &mut output,
                 params.page.as_ptr(),         page.to_owned(),
                 params.n.as_ptr(),            n.to_owned(),
+                params.fail_on.as_ptr(),      options.fail_on as i32,
                 utils::NULL
             ) {
                 0 => Ok( VipsImage{
                     image: output,
                     source: ImageSource::File( path.to_string() ),
+                    pristine: false,
+                    autorotated: false,
                 } ),
                 _ => Err( error_buffer() )
             }
@@ -143,17 +369,18 @@ impl VipsImage {
         }
     }
 
-    pub fn from_pdf_buffer( buffer: &[u8], page: &i32, n: &i32 ) -> Result<VipsImage, String> {
+    pub fn from_pdf_buffer( buffer: &[u8], page: &i32, n: &i32, options: &LoadOptions ) -> Result<VipsImage, String> {
         let params = globals::get_params().unwrap();
-        let options = c_string("").unwrap();
+        let empty = c_string("").unwrap();
 
         unsafe {
             let image = bindings::vips_image_new_from_buffer(
                 buffer.as_ptr() as *const c_void,
                 buffer.len() as usize,
-                options.as_ptr(),
+                empty.as_ptr(),
                 params.page.as_ptr(),           page.to_owned(),
                 params.n.as_ptr(),              n.to_owned(),
+                params.fail_on.as_ptr(),        options.fail_on as i32,
                 utils::NULL
             );
 
@@ -162,7 +389,9 @@ impl VipsImage {
             } else {
                 Ok( VipsImage{
                     image: image,
-                    source: ImageSource::Buffer( buffer.to_vec() ),
+                    source: ImageSource::Buffer( Arc::from( buffer ) ),
+                    pristine: false,
+                    autorotated: false,
                 } )
             }
         }
@@ -183,12 +412,68 @@ impl VipsImage {
             } else {
                 Ok( VipsImage{
                     image: image,
-                    source: ImageSource::Buffer( buffer.to_vec() ),
+                    source: ImageSource::Buffer( Arc::from( buffer ) ),
+                    pristine: true,
+                    autorotated: false,
+                } )
+            }
+        }
+
+    }
+
+    pub fn from_buffer_opts( buffer: &[u8], options: &LoadOptions ) -> Result<VipsImage, String> {
+        VipsImage::from_shared_buffer( Arc::from( buffer ), options )
+    }
+    fn from_shared_buffer( buffer: Arc<[u8]>, options: &LoadOptions ) -> Result<VipsImage, String> {
+        let empty = c_string("").unwrap();
+        let params = globals::get_params().unwrap();
+        unsafe {
+            let image = bindings::vips_image_new_from_buffer(
+                buffer.as_ptr() as *const c_void,
+                buffer.len() as usize,
+                empty.as_ptr(),
+                params.fail_on.as_ptr(),            options.fail_on as i32,
+                utils::NULL
+            );
+
+            if image.is_null() {
+                Err( error_buffer() )
+            } else {
+                Ok( VipsImage{
+                    image: image,
+                    source: ImageSource::Buffer( buffer ),
+                    pristine: true,
+                    autorotated: false,
                 } )
             }
         }
+    }
+    // decodes sequentially while the source is read, the image keeps the source alive
+    pub fn from_source( source: &VipsSource, options: &LoadOptions ) -> Result<VipsImage, String> {
+        let empty = c_string("").unwrap();
+        let params = globals::get_params().unwrap();
+        unsafe {
+            let image = bindings::vips_image_new_from_source(
+                source.source,
+                empty.as_ptr(),
+                params.access.as_ptr(),         bindings::VipsAccess_VIPS_ACCESS_SEQUENTIAL as i32,
+                params.fail_on.as_ptr(),        options.fail_on as i32,
+                utils::NULL
+            );
 
+            if image.is_null() {
+                Err( error_buffer() )
+            } else {
+                Ok( VipsImage{
+                    image: image,
+                    source: ImageSource::None,
+                    pristine: false,
+                    autorotated: false,
+                } )
+            }
+        }
     }
+
     pub fn crop( &self, left: i32, top: i32, width: i32, height: i32 ) -> Result<VipsImage, String> {
         let input: *mut bindings::VipsImage = self.image;
         let mut output: *mut bindings::VipsImage = null();
@@ -197,6 +482,8 @@ impl VipsImage {
                 0 => Ok( VipsImage{
                     image: output,
                     source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
                 } ),
                 _ => Err( error_buffer() )
             }
@@ -210,29 +497,46 @@ impl VipsImage {
                 0 => Ok( VipsImage{
                     image: output,
                     source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
                 } ),
                 _ => Err( error_buffer() )
             }
         } 
     }
 
-    pub fn smart_crop_opts( &self, width: i32, height: i32, options: &SmartcropOptions ) -> Result<VipsImage, String> {
+    pub fn smart_crop_opts( &self, width: i32, height: i32, options: &SmartcropOptions ) -> Result<( VipsImage, SmartcropResult ), String> {
         let input: *mut bindings::VipsImage = self.image;
         let mut output: *mut bindings::VipsImage = null();
         let params = globals::get_params().unwrap();
         unsafe {
+            let mut attention_x: i32 = 0;
+            let mut attention_y: i32 = 0;
+
             match bindings::vips_smartcrop(
                 input,
                 &mut output,
                 width,
                 height,
                 params.interesting.as_ptr(),       options.interesting as i32,
+                params.attention_x.as_ptr(),       &mut attention_x,
+                params.attention_y.as_ptr(),       &mut attention_y,
                 utils::NULL
             ) {
-                0 => Ok( VipsImage{
+                // Note: the extracted area keeps its position as negative offsets
+                0 => Ok( ( VipsImage{
                     image: output,
                     source: self.source.clone(),
-                } ),
+                    pristine: false,
+                    autorotated: false,
+                }, SmartcropResult {
+                    left: -bindings::vips_image_get_xoffset( output ),
+                    top: -bindings::vips_image_get_yoffset( output ),
+                    width: bindings::vips_image_get_width( output ),
+                    height: bindings::vips_image_get_height( output ),
+                    attention_x: attention_x,
+                    attention_y: attention_y,
+                } ) ),
                 _ => Err( error_buffer() )
             }
         }  
@@ -389,7 +693,7 @@ impl VipsImage {
         }
     }
 
-    pub fn jpeg_buffer( &self ) -> Result<Vec<u8>, String> {
+    pub fn jpeg_buffer( &self ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
 
@@ -400,13 +704,13 @@ impl VipsImage {
                 &mut buffer_buf_size,
                 utils::NULL
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn jpeg_buffer_opts( &self, options: &JpegSaveOptions ) -> Result<Vec<u8>, String> {
+    pub fn jpeg_buffer_opts( &self, options: &JpegSaveOptions ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
         let profile = c_string(&options.profile).unwrap();
@@ -433,13 +737,13 @@ impl VipsImage {
                 params.background.as_ptr(),             background_array,
                 utils::NULL
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn png_buffer( &self ) -> Result<Vec<u8>, String> {
+    pub fn png_buffer( &self ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
 
@@ -450,13 +754,13 @@ impl VipsImage {
                 &mut buffer_buf_size,
                 utils::NULL,
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn png_buffer_opts( &self, options: &PngSaveOptions ) -> Result<Vec<u8>, String> {
+    pub fn png_buffer_opts( &self, options: &PngSaveOptions ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
         let params = globals::get_params().unwrap();
@@ -482,13 +786,13 @@ impl VipsImage {
                 params.background.as_ptr(),         background_array,
                 utils::NULL
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn webp_buffer( &self ) -> Result<Vec<u8>, String> {
+    pub fn webp_buffer( &self ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
 
@@ -499,13 +803,13 @@ impl VipsImage {
                 &mut buffer_buf_size,
                 utils::NULL,
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn webp_buffer_opts( &self, options: &WebPSaveOptions ) -> Result<Vec<u8>, String> {
+    pub fn webp_buffer_opts( &self, options: &WebPSaveOptions ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
         let params = globals::get_params().unwrap();
@@ -524,13 +828,13 @@ impl VipsImage {
                 params.background.as_ptr(),         background_array,
                 utils::NULL
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn avif_buffer( &self ) -> Result<Vec<u8>, String> {
+    pub fn avif_buffer( &self ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
 
@@ -541,13 +845,13 @@ impl VipsImage {
                 &mut buffer_buf_size,
                 utils::NULL
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn avif_buffer_opts( &self, options: &HeifsaveOptions ) -> Result<Vec<u8>, String> {
+    pub fn avif_buffer_opts( &self, options: &HeifsaveOptions ) -> Result<VipsBuffer, String> {
         let mut buffer_buf_size: usize = 0;
         let mut buffer_out = null();
         let params = globals::get_params().unwrap();
@@ -566,7 +870,108 @@ impl VipsImage {
                 params.compression.as_ptr(),        options.compression,
                 utils::NULL
             ) {
-                0 => Ok( utils::get_buffer( buffer_out, buffer_buf_size ) ),
+                0 => Ok( VipsBuffer::from_raw( buffer_out, buffer_buf_size ) ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn jpeg_target_opts( &self, target: &VipsTarget, options: &JpegSaveOptions ) -> Result<(), String> {
+        let profile = c_string(&options.profile).unwrap();
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let background_array = bindings::vips_array_double_new(options.background.as_ptr(), options.background.len() as i32);
+
+            match bindings::vips_jpegsave_target(
+                self.image as *mut bindings::_VipsImage,
+                target.target,
+                params.page_height.as_ptr(),            options.page_height,
+                params.q.as_ptr(),                      options.q,
+                params.profile.as_ptr(),                profile.as_ptr(),
+                params.optimize_coding.as_ptr(),        options.optimize_coding as i32,
+                params.interlace.as_ptr(),              options.interlace as i32,
+                params.no_sub_sample.as_ptr(),          options.no_subsample as i32,
+                params.trellis_quant.as_ptr(),          options.trellis_quant as i32,
+                params.overshoot_deringing.as_ptr(),    options.overshoot_deringing as i32,
+                params.optimize_scans.as_ptr(),         options.optimize_scans as i32,
+                params.quant_table.as_ptr(),            options.quant_table,
+                params.strip.as_ptr(),                  options.strip as i32,
+                params.background.as_ptr(),             background_array,
+                utils::NULL
+            ) {
+                0 => Ok( () ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn png_target_opts( &self, target: &VipsTarget, options: &PngSaveOptions ) -> Result<(), String> {
+        let params = globals::get_params().unwrap();
+        let profile = c_string(&options.profile).unwrap();
+
+        unsafe {
+            let background_array = bindings::vips_array_double_new(options.background.as_ptr(), options.background.len() as i32);
+
+            match bindings::vips_pngsave_target(
+                self.image as *mut bindings::_VipsImage,
+                target.target,
+                params.compression.as_ptr(),        options.compression,
+                params.interlace.as_ptr(),          options.interlace as i32,
+                params.page_height.as_ptr(),        options.page_height,
+                params.profile.as_ptr(),            profile.as_ptr(),
+                params.filter.as_ptr(),             options.filter,
+                params.palette.as_ptr(),            options.palette as i32,
+                params.colours.as_ptr(),            options.colours,
+                params.q.as_ptr(),                  options.q,
+                params.dither.as_ptr(),             options.dither,
+                params.strip.as_ptr(),              options.strip as i32,
+                params.background.as_ptr(),         background_array,
+                utils::NULL
+            ) {
+                0 => Ok( () ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn webp_target_opts( &self, target: &VipsTarget, options: &WebPSaveOptions ) -> Result<(), String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let background_array = bindings::vips_array_double_new(options.background.as_ptr(), options.background.len() as i32);
+
+            match bindings::vips_webpsave_target(
+                self.image as *mut bindings::_VipsImage,
+                target.target,
+                params.page_height.as_ptr(),        options.page_height,
+                params.q.as_ptr(),                  options.q,
+                params.strip.as_ptr(),              options.strip as i32,
+                params.background.as_ptr(),         background_array,
+                utils::NULL
+            ) {
+                0 => Ok( () ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn avif_target_opts( &self, target: &VipsTarget, options: &HeifsaveOptions ) -> Result<(), String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let background_array = bindings::vips_array_double_new(options.background.as_ptr(), options.background.len() as i32);
+
+            match bindings::vips_heifsave_target(
+                self.image as *mut bindings::_VipsImage,
+                target.target,
+                params.page_height.as_ptr(),        options.page_height,
+                params.q.as_ptr(),                  options.q,
+                params.background.as_ptr(),         background_array,
+                params.compression.as_ptr(),        options.compression,
+                utils::NULL
+            ) {
+                0 => Ok( () ),
                 _ => Err( error_buffer() )
             }
         }
@@ -615,7 +1020,7 @@ impl VipsImage {
 
     pub fn raw_buffer( &self ) -> Result<Vec<u8>, String> {
         match self.source {
-            ImageSource::Buffer( ref buffer ) => Ok( buffer.clone() ),
+            ImageSource::Buffer( ref buffer ) => Ok( buffer.to_vec() ),
             ImageSource::File( ref path ) => {
                 let buffer = fs::read( path ).unwrap();
                 Ok( buffer )
@@ -637,22 +1042,609 @@ impl VipsImage {
                 0 => Ok( VipsImage{
                     image: output,
                     source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
                 } ),
                 _ => Err( error_buffer() )
             }
         }
     }
 
-    pub fn to_buffer( &self ) -> Result<Vec<u8>, String> {
-        match self.get_format().unwrap() {
-            VipsFormat::JPEG => self.jpeg_buffer(),
-            VipsFormat::PNG => self.png_buffer(),
-            VipsFormat::WEBP => self.webp_buffer(),
-            VipsFormat::AVIF => self.avif_buffer(),
-            VipsFormat::SVG => self.raw_buffer(),
+    pub fn resize_opts( &self, scale: f64, options: &ResizeOptions ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_resize(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                scale,
+                params.kernel.as_ptr(),             options.kernel as i32,
+                params.vscale.as_ptr(),             options.vscale,
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
         }
     }
 
+    /// size of the source the image was loaded from, read again for files
+    pub fn source_size( &self ) -> Option<u64> {
+        match self.source {
+            ImageSource::File( ref path ) => fs::metadata( path ).ok().map( |metadata| metadata.len() ),
+            ImageSource::Buffer( ref buffer ) => Some( buffer.len() as u64 ),
+            ImageSource::None => None
+        }
+    }
+
+    /// the header of the source read again, as a reload through thumbnail would, a buffer is shared rather than copied
+    pub fn reopen( &self, options: &LoadOptions ) -> Result<VipsImage, String> {
+        match self.source {
+            ImageSource::File( ref path ) => VipsImage::from_file_opts( path, options ),
+            ImageSource::Buffer( ref buffer ) => VipsImage::from_shared_buffer( buffer.clone(), options ),
+            ImageSource::None => Err( "no source".to_string() )
+        }
+    }
+
+    pub fn thumbnail( &self, width: i32, options: &ThumbnailOptions ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+        let mut output: *mut bindings::VipsImage = null();
+
+        let result = match self.source {
+            ImageSource::File( ref path ) => {
+                let filename = c_string( path ).unwrap();
+                unsafe {
+                    bindings::vips_thumbnail(
+                        filename.as_ptr(),
+                        &mut output,
+                        width,
+                        params.height.as_ptr(),             options.height,
+                        params.size.as_ptr(),               options.size as i32,
+                        params.no_rotate.as_ptr(),          options.no_rotate as i32,
+                        params.linear.as_ptr(),             options.linear as i32,
+                        params.fail_on.as_ptr(),            options.fail_on as i32,
+                        utils::NULL
+                    )
+                }
+            },
+            ImageSource::Buffer( ref buffer ) => {
+                unsafe {
+                    bindings::vips_thumbnail_buffer(
+                        buffer.as_ptr() as *mut c_void,
+                        buffer.len(),
+                        &mut output,
+                        width,
+                        params.height.as_ptr(),             options.height,
+                        params.size.as_ptr(),               options.size as i32,
+                        params.no_rotate.as_ptr(),          options.no_rotate as i32,
+                        params.linear.as_ptr(),             options.linear as i32,
+                        params.fail_on.as_ptr(),            options.fail_on as i32,
+                        utils::NULL
+                    )
+                }
+            },
+            ImageSource::None => return Err( "no source".to_string() )
+        };
+
+        match result {
+            0 => Ok( VipsImage{
+                image: output,
+                source: self.source.clone(),
+                pristine: false,
+                autorotated: false,
+            } ),
+            _ => Err( error_buffer() )
+        }
+    }
+
+    pub fn autorot( &self ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_autorot(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                utils::NULL
+            ) {
+                // vips_autorot removes the orientation tag, the result is the same image the loader
+                // would produce with rotation, so it stays pristine
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: self.pristine,
+                    autorotated: true,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    /// decodes every pixel into memory, so later operations never go back to the source
+    pub fn copy_memory( &self ) -> Result<VipsImage, String> {
+        unsafe {
+            let output = bindings::vips_image_copy_memory( self.image as *mut bindings::_VipsImage );
+
+            match output.is_null() {
+                true => Err( error_buffer() ),
+                // nothing is reloaded from the source anymore, shrink-on-load doesn't apply
+                false => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: self.autorotated,
+                } )
+            }
+        }
+    }
+
+    /// a new image computing the same pixels, signals connected to it and killing it don't reach the original,
+    /// written directly rather than through vips_copy, which the operation cache would share between callers
+    pub fn copy( &self ) -> Result<VipsImage, String> {
+        unsafe {
+            let output = bindings::vips_image_new();
+
+            match bindings::vips_image_write( self.image, output ) {
+                // the pixels are the same, the source can still shrink them on load
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: self.pristine,
+                    autorotated: self.autorotated,
+                } ),
+                _ => {
+                    bindings::g_object_unref( output as *mut c_void );
+                    Err( error_buffer() )
+                }
+            }
+        }
+    }
+
+    /// runs compute, calling eval with the percentage done while this image (or one built from it) is computed,
+    /// the computation is killed as soon as eval returns false
+    pub fn watch_eval<R, F>( &self, eval: &mut dyn FnMut( i32 ) -> bool, compute: F ) -> R where F: FnOnce() -> R {
+        let signal = c_string( "eval" ).unwrap();
+        let mut watch = EvalWatch { eval: eval, killed: false };
+
+        unsafe {
+            bindings::vips_image_set_progress( self.image, 1 );
+            let handler_id = bindings::g_signal_connect_data(
+                self.image as *mut c_void,
+                signal.as_ptr(),
+                Some( std::mem::transmute::<*const (), unsafe extern "C" fn()>( eval_handler as *const () ) ),
+                &mut watch as *mut EvalWatch as *mut c_void,
+                None,
+                0
+            );
+
+            let result = compute();
+
+            bindings::g_signal_handler_disconnect( self.image as *mut c_void, handler_id );
+            bindings::vips_image_set_progress( self.image, 0 );
+            if watch.killed {
+                bindings::vips_image_set_kill( self.image, 0 );
+            }
+            result
+        }
+    }
+
+    /// makes the evaluation of the image, and of anything computed from it, stop with an error
+    pub fn set_kill( &self, kill: bool ) {
+        unsafe {
+            bindings::vips_image_set_kill( self.image, kill as i32 );
+        }
+    }
+
+    /// another reference to the same image, dropped independently
+    pub fn shared( &self ) -> VipsImage {
+        unsafe {
+            bindings::g_object_ref( self.image as *mut c_void );
+        }
+        VipsImage{
+            image: self.image,
+            source: self.source.clone(),
+            pristine: false,
+            autorotated: self.autorotated,
+        }
+    }
+
+    pub fn rot( &self, angle: Angle ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_rot(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                angle as bindings::VipsAngle,
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn rotate_opts( &self, angle: f64, options: &RotateOptions ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+            let background_array = VipsDoubleArray::new( &options.background );
+
+            match bindings::vips_rotate(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                angle,
+                params.background.as_ptr(),         background_array.as_ptr(),
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn affine( &self, matrix: [f64; 4], options: &AffineOptions ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+        let interpolator = match new_interpolate( options.interpolate ) {
+            Ok( interpolator ) => interpolator,
+            Err( err ) => return Err( err )
+        };
+
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+            let background_array = VipsDoubleArray::new( &options.background );
+
+            let result = bindings::vips_affine(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                matrix[0],
+                matrix[1],
+                matrix[2],
+                matrix[3],
+                params.interpolate.as_ptr(),        interpolator,
+                params.odx.as_ptr(),                options.odx,
+                params.ody.as_ptr(),                options.ody,
+                params.idx.as_ptr(),                options.idx,
+                params.idy.as_ptr(),                options.idy,
+                params.background.as_ptr(),         background_array.as_ptr(),
+                utils::NULL
+            );
+            bindings::g_object_unref( interpolator as *mut c_void );
+
+            match result {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    /// two band image where each pixel holds its own x and y
+    pub fn xyz( width: i32, height: i32 ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_xyz( &mut output, width, height, utils::NULL ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: ImageSource::None,
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn bandjoin_const( &self, constant: f64 ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_bandjoin_const1( self.image as *mut bindings::_VipsImage, &mut output, constant, utils::NULL ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    /// multiplies every pixel by a row-major matrix of `width` input bands and `height` output bands
+    pub fn recomb( &self, matrix: &[f64], width: i32, height: i32 ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+            let matrix_image = bindings::vips_image_new_matrix_from_array( width, height, matrix.as_ptr(), matrix.len() as i32 );
+            if matrix_image.is_null() {
+                return Err( error_buffer() );
+            }
+
+            let result = bindings::vips_recomb( self.image as *mut bindings::_VipsImage, &mut output, matrix_image, utils::NULL );
+            bindings::g_object_unref( matrix_image as *mut c_void );
+
+            match result {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn extract_bands( &self, band: i32, n: i32 ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_extract_band(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                band,
+                params.n.as_ptr(),                  n,
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn divide( &self, right: &VipsImage ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_divide( self.image as *mut bindings::_VipsImage, right.image as *mut bindings::_VipsImage, &mut output, utils::NULL ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    /// resamples the image, each pixel of `index` holding the x and y to read from
+    pub fn mapim( &self, index: &VipsImage, options: &MapimOptions ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+        let interpolator = match new_interpolate( options.interpolate ) {
+            Ok( interpolator ) => interpolator,
+            Err( err ) => return Err( err )
+        };
+
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+            let background_array = VipsDoubleArray::new( &options.background );
+
+            let result = bindings::vips_mapim(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                index.image as *mut bindings::_VipsImage,
+                params.interpolate.as_ptr(),        interpolator,
+                params.background.as_ptr(),         background_array.as_ptr(),
+                utils::NULL
+            );
+            bindings::g_object_unref( interpolator as *mut c_void );
+
+            match result {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn gaussblur( &self, sigma: f64 ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_gaussblur( self.image as *mut bindings::_VipsImage, &mut output, sigma, utils::NULL ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    /// blends `overlay` over the image with its top-left corner at x, y
+    pub fn composite2( &self, overlay: &VipsImage, mode: BlendMode, x: i32, y: i32 ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_composite2(
+                self.image as *mut bindings::_VipsImage,
+                overlay.image as *mut bindings::_VipsImage,
+                &mut output,
+                mode as bindings::VipsBlendMode,
+                params.x.as_ptr(),                  x,
+                params.y.as_ptr(),                  y,
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn flip( &self, direction: Direction ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_flip(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                direction as bindings::VipsDirection,
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn get_point( &self, x: i32, y: i32 ) -> Result<Vec<f64>, String> {
+        unsafe {
+            let mut vector: *mut f64 = null();
+            let mut n: i32 = 0;
+
+            match bindings::vips_getpoint(
+                self.image as *mut bindings::_VipsImage,
+                &mut vector,
+                &mut n,
+                x,
+                y,
+                utils::NULL
+            ) {
+                0 => {
+                    // Note: the vector is allocated by libvips and must be freed
+                    let point = std::slice::from_raw_parts( vector, n as usize ).to_vec();
+                    bindings::g_free( vector as *mut c_void );
+                    Ok( point )
+                },
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn find_trim( &self, options: &FindTrimOptions ) -> Result<[i32; 4], String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let mut left: i32 = 0;
+            let mut top: i32 = 0;
+            let mut width: i32 = 0;
+            let mut height: i32 = 0;
+            let background_array = VipsDoubleArray::new( &options.background );
+
+            match bindings::vips_find_trim(
+                self.image as *mut bindings::_VipsImage,
+                &mut left,
+                &mut top,
+                &mut width,
+                &mut height,
+                params.threshold.as_ptr(),          options.threshold,
+                params.background.as_ptr(),         background_array.as_ptr(),
+                utils::NULL
+            ) {
+                0 => Ok( [ left, top, width, height ] ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn colourspace( &self, space: i32 ) -> Result<VipsImage, String> {
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+
+            match bindings::vips_colourspace(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                space,
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn gravity( &self, direction: CompassDirection, width: i32, height: i32, options: &GravityOptions ) -> Result<VipsImage, String> {
+        let params = globals::get_params().unwrap();
+
+        unsafe {
+            let mut output: *mut bindings::VipsImage = null();
+            let background_array = VipsDoubleArray::new( &options.background );
+
+            match bindings::vips_gravity(
+                self.image as *mut bindings::_VipsImage,
+                &mut output,
+                direction as bindings::VipsCompassDirection,
+                width,
+                height,
+                params.extend.as_ptr(),             options.extend as i32,
+                params.background.as_ptr(),         background_array.as_ptr(),
+                utils::NULL
+            ) {
+                0 => Ok( VipsImage{
+                    image: output,
+                    source: self.source.clone(),
+                    pristine: false,
+                    autorotated: false,
+                } ),
+                _ => Err( error_buffer() )
+            }
+        }
+    }
+
+    pub fn to_buffer( &self ) -> Result<Vec<u8>, String> {
+        let buffer = match self.get_format().unwrap() {
+            VipsFormat::JPEG => self.jpeg_buffer(),
+            VipsFormat::PNG => self.png_buffer(),
+            VipsFormat::WEBP => self.webp_buffer(),
+            VipsFormat::AVIF => self.avif_buffer(),
+            VipsFormat::SVG => return self.raw_buffer(),
+        };
+        buffer.map( |buffer| buffer.to_vec() )
+    }
+
 
 }
 
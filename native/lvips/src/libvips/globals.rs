@@ -25,10 +25,31 @@ pub struct GlobalsParams {
 
     pub interesting:            CString,
 
+    pub vscale:                 CString,
+    pub extend:                 CString,
+
+    pub height:                 CString,
+    pub size:                   CString,
+    pub no_rotate:              CString,
+    pub linear:                 CString,
+    pub kernel:                 CString,
+    pub threshold:              CString,
+    pub attention_x:            CString,
+    pub attention_y:            CString,
+    pub interpolate:            CString,
+    pub odx:                    CString,
+    pub ody:                    CString,
+    pub idx:                    CString,
+    pub idy:                    CString,
+    pub x:                      CString,
+    pub y:                      CString,
+
     pub vips_loader:            CString,
 
     pub n:                      CString,
     pub page:                      CString,
+    pub access:                 CString,
+    pub fail_on:                CString,
  
 }
 
@@ -56,10 +77,31 @@ impl Default for GlobalsParams {
 
             interesting:            c_string( "interesting" ).unwrap(),
 
+            vscale:                 c_string( "vscale" ).unwrap(),
+            extend:                 c_string( "extend" ).unwrap(),
+
+            height:                 c_string( "height" ).unwrap(),
+            size:                   c_string( "size" ).unwrap(),
+            no_rotate:              c_string( "no_rotate" ).unwrap(),
+            linear:                 c_string( "linear" ).unwrap(),
+            kernel:                 c_string( "kernel" ).unwrap(),
+            threshold:              c_string( "threshold" ).unwrap(),
+            attention_x:            c_string( "attention_x" ).unwrap(),
+            attention_y:            c_string( "attention_y" ).unwrap(),
+            interpolate:            c_string( "interpolate" ).unwrap(),
+            odx:                    c_string( "odx" ).unwrap(),
+            ody:                    c_string( "ody" ).unwrap(),
+            idx:                    c_string( "idx" ).unwrap(),
+            idy:                    c_string( "idy" ).unwrap(),
+            x:                      c_string( "x" ).unwrap(),
+            y:                      c_string( "y" ).unwrap(),
+
             vips_loader:            c_string( "vips-loader" ).unwrap(),
 
             n:                      c_string( "n" ).unwrap(),
             page:                   c_string( "page" ).unwrap(),
+            access:                 c_string( "access" ).unwrap(),
+            fail_on:                c_string( "fail_on" ).unwrap(),
         }
     }
 }
@@ -238,8 +238,10 @@ pub enum Interesting {
     Low = 4,
     ///  `High` -> VIPS_INTERESTING_HIGH = 5
     High = 5,
-    ///  `Last` -> VIPS_INTERESTING_LAST = 6
-    Last = 6,
+    ///  `All` -> VIPS_INTERESTING_ALL = 6
+    All = 6,
+    ///  `Last` -> VIPS_INTERESTING_LAST = 7
+    Last = 7,
 }
 
 /// Options for smartcrop operation
@@ -252,10 +254,28 @@ pub struct SmartcropOptions {
     ///  `Attention` -> VIPS_INTERESTING_ATTENTION = 3 [DEFAULT]
     ///  `Low` -> VIPS_INTERESTING_LOW = 4
     ///  `High` -> VIPS_INTERESTING_HIGH = 5
-    ///  `Last` -> VIPS_INTERESTING_LAST = 6
+    ///  `All` -> VIPS_INTERESTING_ALL = 6
+    ///  `Last` -> VIPS_INTERESTING_LAST = 7
     pub interesting: Interesting,
 }
 
+/// Outcome of smartcrop operation
+#[derive(Clone, Debug)]
+pub struct SmartcropResult {
+    /// left: `i32` -> Left edge of the crop
+    pub left: i32,
+    /// top: `i32` -> Top edge of the crop
+    pub top: i32,
+    /// width: `i32` -> Width of the crop
+    pub width: i32,
+    /// height: `i32` -> Height of the crop
+    pub height: i32,
+    /// attention_x: `i32` -> Horizontal position of attention centre
+    pub attention_x: i32,
+    /// attention_y: `i32` -> Vertical position of attention centre
+    pub attention_y: i32,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum ForeignHeifCompression {
     ///  `Hevc` -> VIPS_FOREIGN_HEIF_COMPRESSION_HEVC = 1
@@ -407,3 +427,440 @@ impl std::default::Default for SmartcropOptions {
         }
     }
 }
+
+#[derive(Copy, Clone, Debug)]
+pub enum Kernel {
+    ///  `Nearest` -> VIPS_KERNEL_NEAREST = 0
+    Nearest = 0,
+    ///  `Linear` -> VIPS_KERNEL_LINEAR = 1
+    Linear = 1,
+    ///  `Cubic` -> VIPS_KERNEL_CUBIC = 2
+    Cubic = 2,
+    ///  `Mitchell` -> VIPS_KERNEL_MITCHELL = 3
+    Mitchell = 3,
+    ///  `Lanczos2` -> VIPS_KERNEL_LANCZOS2 = 4
+    Lanczos2 = 4,
+    ///  `Lanczos3` -> VIPS_KERNEL_LANCZOS3 = 5
+    Lanczos3 = 5,
+    ///  `Mks2013` -> VIPS_KERNEL_MKS2013 = 6
+    Mks2013 = 6,
+    ///  `Mks2021` -> VIPS_KERNEL_MKS2021 = 7
+    Mks2021 = 7,
+    ///  `Last` -> VIPS_KERNEL_LAST = 8
+    Last = 8,
+}
+
+/// Options for resize operation
+#[derive(Clone, Debug)]
+pub struct ResizeOptions {
+    /// kernel: `Kernel` -> Resampling kernel
+    ///  `Nearest` -> VIPS_KERNEL_NEAREST = 0
+    ///  `Linear` -> VIPS_KERNEL_LINEAR = 1
+    ///  `Cubic` -> VIPS_KERNEL_CUBIC = 2
+    ///  `Mitchell` -> VIPS_KERNEL_MITCHELL = 3
+    ///  `Lanczos2` -> VIPS_KERNEL_LANCZOS2 = 4
+    ///  `Lanczos3` -> VIPS_KERNEL_LANCZOS3 = 5 [DEFAULT]
+    ///  `Mks2013` -> VIPS_KERNEL_MKS2013 = 6
+    ///  `Mks2021` -> VIPS_KERNEL_MKS2021 = 7
+    ///  `Last` -> VIPS_KERNEL_LAST = 8
+    pub kernel: Kernel,
+    /// vscale: `f64` -> Vertical scale image by this factor
+    /// min: 0, max: 10000000, default: 0
+    pub vscale: f64,
+}
+
+impl std::default::Default for ResizeOptions {
+    fn default() -> Self {
+        ResizeOptions {
+            kernel: Kernel::Lanczos3,
+            vscale: f64::from(0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Interpretation {
+    ///  `Error` -> VIPS_INTERPRETATION_ERROR = -1
+    Error = -1,
+    ///  `Multiband` -> VIPS_INTERPRETATION_MULTIBAND = 0
+    Multiband = 0,
+    ///  `BW` -> VIPS_INTERPRETATION_B_W = 1
+    BW = 1,
+    ///  `Histogram` -> VIPS_INTERPRETATION_HISTOGRAM = 10
+    Histogram = 10,
+    ///  `Xyz` -> VIPS_INTERPRETATION_XYZ = 12
+    Xyz = 12,
+    ///  `Lab` -> VIPS_INTERPRETATION_LAB = 13
+    Lab = 13,
+    ///  `Cmyk` -> VIPS_INTERPRETATION_CMYK = 15
+    Cmyk = 15,
+    ///  `Labq` -> VIPS_INTERPRETATION_LABQ = 16
+    Labq = 16,
+    ///  `Rgb` -> VIPS_INTERPRETATION_RGB = 17
+    Rgb = 17,
+    ///  `Cmc` -> VIPS_INTERPRETATION_CMC = 18
+    Cmc = 18,
+    ///  `Lch` -> VIPS_INTERPRETATION_LCH = 19
+    Lch = 19,
+    ///  `Labs` -> VIPS_INTERPRETATION_LABS = 21
+    Labs = 21,
+    ///  `Srgb` -> VIPS_INTERPRETATION_sRGB = 22
+    Srgb = 22,
+    ///  `Yxy` -> VIPS_INTERPRETATION_YXY = 23
+    Yxy = 23,
+    ///  `Fourier` -> VIPS_INTERPRETATION_FOURIER = 24
+    Fourier = 24,
+    ///  `Rgb16` -> VIPS_INTERPRETATION_RGB16 = 25
+    Rgb16 = 25,
+    ///  `Grey16` -> VIPS_INTERPRETATION_GREY16 = 26
+    Grey16 = 26,
+    ///  `Matrix` -> VIPS_INTERPRETATION_MATRIX = 27
+    Matrix = 27,
+    ///  `Scrgb` -> VIPS_INTERPRETATION_scRGB = 28
+    Scrgb = 28,
+    ///  `Hsv` -> VIPS_INTERPRETATION_HSV = 29
+    Hsv = 29,
+    ///  `Last` -> VIPS_INTERPRETATION_LAST = 30
+    Last = 30,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum CompassDirection {
+    ///  `Centre` -> VIPS_COMPASS_DIRECTION_CENTRE = 0
+    Centre = 0,
+    ///  `North` -> VIPS_COMPASS_DIRECTION_NORTH = 1
+    North = 1,
+    ///  `East` -> VIPS_COMPASS_DIRECTION_EAST = 2
+    East = 2,
+    ///  `South` -> VIPS_COMPASS_DIRECTION_SOUTH = 3
+    South = 3,
+    ///  `West` -> VIPS_COMPASS_DIRECTION_WEST = 4
+    West = 4,
+    ///  `NorthEast` -> VIPS_COMPASS_DIRECTION_NORTH_EAST = 5
+    NorthEast = 5,
+    ///  `SouthEast` -> VIPS_COMPASS_DIRECTION_SOUTH_EAST = 6
+    SouthEast = 6,
+    ///  `SouthWest` -> VIPS_COMPASS_DIRECTION_SOUTH_WEST = 7
+    SouthWest = 7,
+    ///  `NorthWest` -> VIPS_COMPASS_DIRECTION_NORTH_WEST = 8
+    NorthWest = 8,
+    ///  `Last` -> VIPS_COMPASS_DIRECTION_LAST = 9
+    Last = 9,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Extend {
+    ///  `Black` -> VIPS_EXTEND_BLACK = 0
+    Black = 0,
+    ///  `Copy` -> VIPS_EXTEND_COPY = 1
+    Copy = 1,
+    ///  `Repeat` -> VIPS_EXTEND_REPEAT = 2
+    Repeat = 2,
+    ///  `Mirror` -> VIPS_EXTEND_MIRROR = 3
+    Mirror = 3,
+    ///  `White` -> VIPS_EXTEND_WHITE = 4
+    White = 4,
+    ///  `Background` -> VIPS_EXTEND_BACKGROUND = 5
+    Background = 5,
+    ///  `Last` -> VIPS_EXTEND_LAST = 6
+    Last = 6,
+}
+
+/// Options for gravity operation
+#[derive(Clone, Debug)]
+pub struct GravityOptions {
+    /// extend: `Extend` -> How to generate the extra pixels
+    ///  `Black` -> VIPS_EXTEND_BLACK = 0 [DEFAULT]
+    ///  `Copy` -> VIPS_EXTEND_COPY = 1
+    ///  `Repeat` -> VIPS_EXTEND_REPEAT = 2
+    ///  `Mirror` -> VIPS_EXTEND_MIRROR = 3
+    ///  `White` -> VIPS_EXTEND_WHITE = 4
+    ///  `Background` -> VIPS_EXTEND_BACKGROUND = 5
+    ///  `Last` -> VIPS_EXTEND_LAST = 6
+    pub extend: Extend,
+    /// background: `Vec<f64>` -> Color for background pixels
+    pub background: Vec<f64>,
+}
+
+impl std::default::Default for GravityOptions {
+    fn default() -> Self {
+        GravityOptions {
+            extend: Extend::Black,
+            background: vec![0.0],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Size {
+    ///  `Both` -> VIPS_SIZE_BOTH = 0
+    Both = 0,
+    ///  `Up` -> VIPS_SIZE_UP = 1
+    Up = 1,
+    ///  `Down` -> VIPS_SIZE_DOWN = 2
+    Down = 2,
+    ///  `Force` -> VIPS_SIZE_FORCE = 3
+    Force = 3,
+    ///  `Last` -> VIPS_SIZE_LAST = 4
+    Last = 4,
+}
+
+/// Options for thumbnail operation
+#[derive(Clone, Debug)]
+pub struct ThumbnailOptions {
+    /// height: `i32` -> Size to this height
+    /// min: 1, max: 10000000, default: 1
+    pub height: i32,
+    /// size: `Size` -> Only upsize, only downsize, or both
+    ///  `Both` -> VIPS_SIZE_BOTH = 0 [DEFAULT]
+    ///  `Up` -> VIPS_SIZE_UP = 1
+    ///  `Down` -> VIPS_SIZE_DOWN = 2
+    ///  `Force` -> VIPS_SIZE_FORCE = 3
+    ///  `Last` -> VIPS_SIZE_LAST = 4
+    pub size: Size,
+    /// no_rotate: `bool` -> Don't use orientation tags to rotate image upright
+    /// default: false
+    pub no_rotate: bool,
+    /// linear: `bool` -> Reduce in linear light
+    /// default: false
+    pub linear: bool,
+    /// fail_on: `FailOn` -> Error level to fail on
+    pub fail_on: FailOn,
+}
+
+impl std::default::Default for ThumbnailOptions {
+    fn default() -> Self {
+        ThumbnailOptions {
+            height: i32::from(1),
+            size: Size::Both,
+            no_rotate: false,
+            linear: false,
+            fail_on: FailOn::None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Angle {
+    ///  `D0` -> VIPS_ANGLE_D0 = 0
+    D0 = 0,
+    ///  `D90` -> VIPS_ANGLE_D90 = 1
+    D90 = 1,
+    ///  `D180` -> VIPS_ANGLE_D180 = 2
+    D180 = 2,
+    ///  `D270` -> VIPS_ANGLE_D270 = 3
+    D270 = 3,
+    ///  `Last` -> VIPS_ANGLE_LAST = 4
+    Last = 4,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Direction {
+    ///  `Horizontal` -> VIPS_DIRECTION_HORIZONTAL = 0
+    Horizontal = 0,
+    ///  `Vertical` -> VIPS_DIRECTION_VERTICAL = 1
+    Vertical = 1,
+    ///  `Last` -> VIPS_DIRECTION_LAST = 2
+    Last = 2,
+}
+
+/// Options for rotate operation
+#[derive(Clone, Debug)]
+pub struct RotateOptions {
+    /// background: `Vec<f64>` -> Background value
+    pub background: Vec<f64>,
+}
+
+impl std::default::Default for RotateOptions {
+    fn default() -> Self {
+        RotateOptions {
+            background: vec![0.0],
+        }
+    }
+}
+
+/// Options for find_trim operation
+#[derive(Clone, Debug)]
+pub struct FindTrimOptions {
+    /// threshold: `f64` -> Object threshold
+    /// min: 0, max: inf, default: 10
+    pub threshold: f64,
+    /// background: `Vec<f64>` -> Color for background pixels
+    pub background: Vec<f64>,
+}
+
+impl std::default::Default for FindTrimOptions {
+    fn default() -> Self {
+        FindTrimOptions {
+            threshold: f64::from(10),
+            background: vec![255.0],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Interpolate {
+    ///  `Nearest` -> nearest-neighbour interpolation
+    Nearest,
+    ///  `Bilinear` -> bilinear interpolation [DEFAULT]
+    Bilinear,
+    ///  `Bicubic` -> bicubic interpolation
+    Bicubic,
+    ///  `Lbb` -> reduced halo bicubic
+    Lbb,
+    ///  `Nohalo` -> edge sharpening resampler with halo reduction
+    Nohalo,
+    ///  `Vsqbs` -> B-Splines with antialiasing smoothing
+    Vsqbs,
+}
+
+impl Interpolate {
+    /// nickname libvips knows the interpolator by
+    pub fn nickname( &self ) -> &'static str {
+        match self {
+            Interpolate::Nearest => "nearest",
+            Interpolate::Bilinear => "bilinear",
+            Interpolate::Bicubic => "bicubic",
+            Interpolate::Lbb => "lbb",
+            Interpolate::Nohalo => "nohalo",
+            Interpolate::Vsqbs => "vsqbs",
+        }
+    }
+}
+
+/// Options for affine operation
+#[derive(Clone, Debug)]
+pub struct AffineOptions {
+    /// interpolate: `Interpolate` -> Interpolate pixels with this
+    pub interpolate: Interpolate,
+    /// odx: `f64` -> Horizontal output displacement
+    /// min: -10000000, max: 10000000, default: 0
+    pub odx: f64,
+    /// ody: `f64` -> Vertical output displacement
+    /// min: -10000000, max: 10000000, default: 0
+    pub ody: f64,
+    /// idx: `f64` -> Horizontal input displacement
+    /// min: -10000000, max: 10000000, default: 0
+    pub idx: f64,
+    /// idy: `f64` -> Vertical input displacement
+    /// min: -10000000, max: 10000000, default: 0
+    pub idy: f64,
+    /// background: `Vec<f64>` -> Background value
+    pub background: Vec<f64>,
+}
+
+impl std::default::Default for AffineOptions {
+    fn default() -> Self {
+        AffineOptions {
+            interpolate: Interpolate::Bilinear,
+            odx: f64::from(0),
+            ody: f64::from(0),
+            idx: f64::from(0),
+            idy: f64::from(0),
+            background: vec![0.0],
+        }
+    }
+}
+
+/// Options for mapim operation
+#[derive(Clone, Debug)]
+pub struct MapimOptions {
+    /// interpolate: `Interpolate` -> Interpolate pixels with this
+    pub interpolate: Interpolate,
+    /// background: `Vec<f64>` -> Background value
+    pub background: Vec<f64>,
+}
+
+impl std::default::Default for MapimOptions {
+    fn default() -> Self {
+        MapimOptions {
+            interpolate: Interpolate::Bilinear,
+            background: vec![0.0],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum BlendMode {
+    ///  `Clear` -> VIPS_BLEND_MODE_CLEAR = 0
+    Clear = 0,
+    ///  `Source` -> VIPS_BLEND_MODE_SOURCE = 1
+    Source = 1,
+    ///  `Over` -> VIPS_BLEND_MODE_OVER = 2
+    Over = 2,
+    ///  `In` -> VIPS_BLEND_MODE_IN = 3
+    In = 3,
+    ///  `Out` -> VIPS_BLEND_MODE_OUT = 4
+    Out = 4,
+    ///  `Atop` -> VIPS_BLEND_MODE_ATOP = 5
+    Atop = 5,
+    ///  `Dest` -> VIPS_BLEND_MODE_DEST = 6
+    Dest = 6,
+    ///  `DestOver` -> VIPS_BLEND_MODE_DEST_OVER = 7
+    DestOver = 7,
+    ///  `DestIn` -> VIPS_BLEND_MODE_DEST_IN = 8
+    DestIn = 8,
+    ///  `DestOut` -> VIPS_BLEND_MODE_DEST_OUT = 9
+    DestOut = 9,
+    ///  `DestAtop` -> VIPS_BLEND_MODE_DEST_ATOP = 10
+    DestAtop = 10,
+    ///  `Xor` -> VIPS_BLEND_MODE_XOR = 11
+    Xor = 11,
+    ///  `Add` -> VIPS_BLEND_MODE_ADD = 12
+    Add = 12,
+    ///  `Saturate` -> VIPS_BLEND_MODE_SATURATE = 13
+    Saturate = 13,
+    ///  `Multiply` -> VIPS_BLEND_MODE_MULTIPLY = 14
+    Multiply = 14,
+    ///  `Screen` -> VIPS_BLEND_MODE_SCREEN = 15
+    Screen = 15,
+    ///  `Overlay` -> VIPS_BLEND_MODE_OVERLAY = 16
+    Overlay = 16,
+    ///  `Darken` -> VIPS_BLEND_MODE_DARKEN = 17
+    Darken = 17,
+    ///  `Lighten` -> VIPS_BLEND_MODE_LIGHTEN = 18
+    Lighten = 18,
+    ///  `ColourDodge` -> VIPS_BLEND_MODE_COLOUR_DODGE = 19
+    ColourDodge = 19,
+    ///  `ColourBurn` -> VIPS_BLEND_MODE_COLOUR_BURN = 20
+    ColourBurn = 20,
+    ///  `HardLight` -> VIPS_BLEND_MODE_HARD_LIGHT = 21
+    HardLight = 21,
+    ///  `SoftLight` -> VIPS_BLEND_MODE_SOFT_LIGHT = 22
+    SoftLight = 22,
+    ///  `Difference` -> VIPS_BLEND_MODE_DIFFERENCE = 23
+    Difference = 23,
+    ///  `Exclusion` -> VIPS_BLEND_MODE_EXCLUSION = 24
+    Exclusion = 24,
+    ///  `Last` -> VIPS_BLEND_MODE_LAST = 25
+    Last = 25,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FailOn {
+    ///  `None` -> VIPS_FAIL_ON_NONE = 0 [DEFAULT]
+    None = 0,
+    ///  `Truncated` -> VIPS_FAIL_ON_TRUNCATED = 1
+    Truncated = 1,
+    ///  `Error` -> VIPS_FAIL_ON_ERROR = 2
+    Error = 2,
+    ///  `Warning` -> VIPS_FAIL_ON_WARNING = 3
+    Warning = 3,
+    ///  `Last` -> VIPS_FAIL_ON_LAST = 4
+    Last = 4,
+}
+
+/// Options shared by the loaders
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    /// fail_on: `FailOn` -> Error level to fail on
+    pub fail_on: FailOn,
+}
+
+impl std::default::Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            fail_on: FailOn::None,
+        }
+    }
+}
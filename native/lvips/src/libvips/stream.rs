@@ -0,0 +1,115 @@
+use std::ffi::c_void;
+use std::slice;
+use super::bindings;
+use super::utils::{c_string, null};
+use super::error_buffer;
+
+// fills the buffer with the next input bytes, 0 at the end of the input and -1 on error
+pub type ReadFn = Box<dyn FnMut( &mut [u8] ) -> i64 + Send>;
+// consumes the encoded bytes, returns how many were taken or -1 on error
+pub type WriteFn = Box<dyn FnMut( &[u8] ) -> i64 + Send>;
+
+unsafe extern "C" fn read_handler( _source: *mut bindings::VipsSourceCustom, buffer: *mut c_void, length: i64, data: *mut c_void ) -> i64 {
+    if length <= 0 {
+        return 0;
+    }
+    let reader = unsafe { &mut *( data as *mut ReadFn ) };
+    let buffer = unsafe { slice::from_raw_parts_mut( buffer as *mut u8, length as usize ) };
+    reader( buffer )
+}
+
+unsafe extern "C" fn write_handler( _target: *mut bindings::VipsTargetCustom, buffer: *const c_void, length: i64, data: *mut c_void ) -> i64 {
+    if length <= 0 {
+        return 0;
+    }
+    let writer = unsafe { &mut *( data as *mut WriteFn ) };
+    let buffer = unsafe { slice::from_raw_parts( buffer as *const u8, length as usize ) };
+    writer( buffer )
+}
+
+unsafe extern "C" fn drop_reader( data: *mut c_void, _closure: *mut bindings::GClosure ) {
+    drop( unsafe { Box::from_raw( data as *mut ReadFn ) } );
+}
+
+unsafe extern "C" fn drop_writer( data: *mut c_void, _closure: *mut bindings::GClosure ) {
+    drop( unsafe { Box::from_raw( data as *mut WriteFn ) } );
+}
+
+// the closure is owned by the signal handler and dropped when the object is finalized
+unsafe fn connect<T>( object: *mut c_void, signal: &str, handler: *const (), data: Box<T>, notify: unsafe extern "C" fn( *mut c_void, *mut bindings::GClosure ) ) -> Result<(), String> {
+    let signal = c_string( signal ).unwrap();
+    let data = Box::into_raw( data ) as *mut c_void;
+    unsafe {
+        let handler_id = bindings::g_signal_connect_data(
+            object,
+            signal.as_ptr(),
+            Some( std::mem::transmute::<*const (), unsafe extern "C" fn()>( handler ) ),
+            data,
+            Some( notify ),
+            0
+        );
+        match handler_id {
+            0 => {
+                notify( data, null() );
+                Err( "failed to connect signal".to_string() )
+            },
+            _ => Ok( () )
+        }
+    }
+}
+
+// input pulled on demand by the loader, it keeps its own reference for as long as it reads
+pub struct VipsSource {
+    pub( super ) source: *mut bindings::VipsSource,
+}
+
+impl VipsSource {
+    pub fn new( reader: ReadFn ) -> Result<VipsSource, String> {
+        unsafe {
+            let source = bindings::vips_source_custom_new();
+            if source.is_null() {
+                return Err( error_buffer() );
+            }
+
+            let source = VipsSource { source: source as *mut bindings::VipsSource };
+            connect( source.source as *mut c_void, "read", read_handler as *const (), Box::new( reader ), drop_reader )?;
+            Ok( source )
+        }
+    }
+}
+
+impl Drop for VipsSource {
+    fn drop( &mut self ) {
+        unsafe {
+            bindings::g_object_unref( self.source as *mut c_void );
+        }
+    }
+}
+
+// output pushed by the saver as each chunk is encoded
+pub struct VipsTarget {
+    pub( super ) target: *mut bindings::VipsTarget,
+}
+
+impl VipsTarget {
+    pub fn new( writer: WriteFn ) -> Result<VipsTarget, String> {
+        unsafe {
+            let target = bindings::vips_target_custom_new();
+            if target.is_null() {
+                return Err( error_buffer() );
+            }
+
+            let target = VipsTarget { target: target as *mut bindings::VipsTarget };
+            connect( target.target as *mut c_void, "write", write_handler as *const (), Box::new( writer ), drop_writer )?;
+            Ok( target )
+        }
+    }
+}
+
+impl Drop for VipsTarget {
+    fn drop( &mut self ) {
+        unsafe {
+            bindings::g_object_unref( self.target as *mut c_void );
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use std::ffi::CString;
 use std::ptr::null_mut;
 use std::ffi::{c_void};
+use std::ops::Deref;
+use std::slice;
+use super::bindings;
 
 pub const NULL: *const c_void = null_mut();
 
@@ -15,8 +18,66 @@ pub fn c_string(string: &str) -> Result<CString, &str> {
     }
 }
 
-pub fn get_buffer(buf: *mut c_void, size: usize) -> Vec<u8> {
-    unsafe {
-        Vec::from_raw_parts(buf as *mut u8, size, size )
+// output of a libvips saver, allocated with g_malloc and released with g_free, never by the Rust allocator
+pub struct VipsBuffer {
+    data: *mut c_void,
+    size: usize,
+}
+
+// the memory is owned exclusively by the buffer
+unsafe impl Send for VipsBuffer {}
+
+impl VipsBuffer {
+    pub unsafe fn from_raw( data: *mut c_void, size: usize ) -> VipsBuffer {
+        VipsBuffer {
+            data: data,
+            size: size,
+        }
     }
-}
\ No newline at end of file
+}
+
+impl Deref for VipsBuffer {
+    type Target = [u8];
+
+    fn deref( &self ) -> &[u8] {
+        if self.data.is_null() {
+            return &[];
+        }
+        unsafe {
+            slice::from_raw_parts( self.data as *const u8, self.size )
+        }
+    }
+}
+
+impl Drop for VipsBuffer {
+    fn drop( &mut self ) {
+        unsafe {
+            bindings::g_free( self.data );
+        }
+    }
+}
+
+// array option of an operation, which keeps its own reference, this one is released on drop
+pub struct VipsDoubleArray {
+    array: *mut bindings::VipsArrayDouble,
+}
+
+impl VipsDoubleArray {
+    pub fn new( values: &[f64] ) -> VipsDoubleArray {
+        VipsDoubleArray {
+            array: unsafe { bindings::vips_array_double_new( values.as_ptr(), values.len() as i32 ) },
+        }
+    }
+
+    pub fn as_ptr( &self ) -> *mut bindings::VipsArrayDouble {
+        self.array
+    }
+}
+
+impl Drop for VipsDoubleArray {
+    fn drop( &mut self ) {
+        unsafe {
+            bindings::vips_area_unref( self.array as *mut bindings::VipsArea );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+// fixed number of threads pulling tasks from a shared queue, in the order they were queued
+pub struct WorkerPool {
+    sender: mpsc::Sender<Task>,
+}
+
+impl WorkerPool {
+    // threads started before one fails to spawn exit again as the pool is dropped
+    pub fn new( size: usize, name: &str ) -> Result<WorkerPool, String> {
+        let ( sender, receiver ) = mpsc::channel::<Task>();
+        let receiver = Arc::new( Mutex::new( receiver ) );
+
+        for index in 0..size.max( 1 ) {
+            let receiver = Arc::clone( &receiver );
+            let spawned = thread::Builder::new()
+                .name( format!( "{}-{}", name, index ) )
+                .spawn( move || loop {
+                    // the lock is released as soon as a task is received
+                    let task = match receiver.lock() {
+                        Ok( receiver ) => receiver.recv(),
+                        Err( _ ) => break,
+                    };
+
+                    match task {
+                        // a panicking task must not take the worker down with it
+                        Ok( task ) => { let _ = panic::catch_unwind( AssertUnwindSafe( task ) ); },
+                        Err( _ ) => break,
+                    }
+                } );
+
+            if let Err( err ) = spawned {
+                return Err( format!( "failed to start worker thread: {}", err ) );
+            }
+        }
+
+        Ok( WorkerPool {
+            sender: sender,
+        } )
+    }
+
+    // fails once every worker is gone, the task is dropped then
+    pub fn execute<F>( &self, task: F ) -> Result<(), String> where F: FnOnce() + Send + 'static {
+        match self.sender.send( Box::new( task ) ) {
+            Ok( () ) => Ok( () ),
+            Err( _ ) => Err( "no worker left to run the task".to_string() )
+        }
+    }
+}
@@ -3,10 +3,41 @@ defmodule ElxvipsTest do
   import Elxvips
   # doctest Elxvips.Libvips
 
+  # an image large enough for processing to outlast short timeouts and cancels
+  setup_all do
+    large = Path.join( System.tmp_dir!(), "elxvips_large_#{System.unique_integer( [ :positive ] )}.jpg" )
+    { :ok, _ } = from_file( "test/input.png" ) |> resize( width: 8000 ) |> jpg() |> to_file( large )
+    on_exit( fn -> File.rm( large ) end )
+    %{ large: large }
+  end
+
   test "Set concurreny" do
     assert :ok = set_concurrency( 8 )
   end
 
+  test "Cache limits and memory stats" do
+    { :ok, initial } = get_memory_stats()
+    on_exit( fn ->
+      set_cache( max: initial.cache_max, max_mem: initial.cache_max_mem, max_files: initial.cache_max_files )
+    end )
+
+    assert :ok = set_cache( max: 50, max_mem: 32 * 1024 * 1024, max_files: 10 )
+    assert { :error, _ } = set_cache( max: -1 )
+    assert { :error, "cache max_files must be between 0 and 2147483647, got 2147483648" } = set_cache( max_files: 2147483648 )
+    assert { :error, _ } = set_cache( max: 1, max_mem: -1 )
+
+    { :ok, _ } = from_file( "test/input.png" ) |> resize( width: 100 ) |> png() |> to_bytes()
+
+    assert { :ok, stats = %Elxvips.MemoryStats{} } = get_memory_stats()
+    assert stats.cache_max == 50
+    assert stats.cache_max_mem == 32 * 1024 * 1024
+    assert stats.cache_max_files == 10
+    assert stats.mem_highwater > 0
+    assert stats.mem_highwater >= stats.mem
+
+    assert :ok = drop_cache()
+  end
+
   test "Resize png > jpg" do
     result = from_file( "test/input.png" )
     |> resize( width: 250, height: 300 )
@@ -79,6 +110,605 @@ defmodule ElxvipsTest do
     assert result == { :ok, [ 100, 120 ] }
   end
 
+  test "Resize contain" do
+    result = from_file( "test/input.png" )
+    |> resize( width: 300, height: 300, resize_type: :contain )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 300, 300 ] }
+  end
+
+  test "Resize fill" do
+    result = from_file( "test/input.png" )
+    |> resize( width: 300, height: 100, resize_type: :fill )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 300, 100 ] }
+  end
+
+  test "Resize inside" do
+    result = from_file( "test/input.png" )
+    |> resize( width: 300, height: 300, resize_type: :inside )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 300, 228 ] }
+  end
+
+  test "Resize outside" do
+    { :ok, [ width, height ] } = from_file( "test/input.png" )
+    |> resize( width: 300, height: 300, resize_type: :outside )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert height >= 300
+    assert width > 300
+  end
+
+  test "Resize with gravity" do
+    for gravity <- [ :attention, :entropy, :north, :south_east ] do
+      result = from_file( "test/input.png" )
+      |> resize( width: 200, height: 100, gravity: gravity )
+      |> png()
+      |> to_bytes()
+      |> from_bytes()
+      |> get_image_sizes()
+
+      assert result == { :ok, [ 200, 100 ] }
+    end
+  end
+
+  test "Smartcrop rectangle is returned" do
+    { :ok, %Elxvips.ImageBytes{ :smartcrop => smartcrop } } = from_file( "test/input.png" )
+    |> resize( width: 200, height: 100, gravity: :attention )
+    |> png()
+    |> to_bytes()
+
+    assert %Elxvips.Smartcrop{ :width => 200, :height => 100 } = smartcrop
+    assert smartcrop.left >= 0 and smartcrop.left + smartcrop.width <= smartcrop.image_width
+    assert smartcrop.top >= 0 and smartcrop.top + smartcrop.height <= smartcrop.image_height
+    assert smartcrop.attention_x in 0..smartcrop.image_width
+    assert smartcrop.attention_y in 0..smartcrop.image_height
+
+    assert { :ok, %Elxvips.ImageBytes{ :smartcrop => nil } } = from_file( "test/input.png" )
+    |> resize( width: 200, height: 100, gravity: :north )
+    |> png()
+    |> to_bytes()
+  end
+
+  test "Resize with focal point" do
+    for focal_point <- [ { 0, 0 }, { 0.25, 0.4 }, { 1, 1 } ] do
+      result = from_file( "test/input.png" )
+      |> resize( width: 200, height: 100, focal_point: focal_point )
+      |> png()
+      |> to_bytes()
+      |> from_bytes()
+      |> get_image_sizes()
+
+      assert result == { :ok, [ 200, 100 ] }
+    end
+
+    assert { :error, _ } = from_file( "test/input.png" )
+    |> resize( width: 200, height: 100, focal_point: { 1.5, 0.5 } )
+    |> to_bytes()
+  end
+
+  test "Shrink on load keeps exact sizes" do
+    file = File.open!( "test/vertical.jpg", [ :read ] )
+    bytes = IO.binread( file, :eof )
+
+    from_file_sizes = from_file( "test/vertical.jpg" )
+    |> resize( width: 125 )
+    |> jpg()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    from_bytes_sizes = from_bytes( bytes )
+    |> resize( width: 125 )
+    |> jpg()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert from_file_sizes == { :ok, [ 125, 150 ] }
+    assert from_bytes_sizes == { :ok, [ 125, 150 ] }
+
+    # fail_on is passed on to the reload, which still shrinks while decoding
+    { :ok, %Elxvips.ImageBytes{ :bytes => thumbnail } } = from_file( "test/vertical.jpg" ) |> resize( width: 125 ) |> png() |> to_bytes()
+    for image <- [ from_file( "test/vertical.jpg" ), from_bytes( bytes ) ] do
+      { :ok, %Elxvips.ImageBytes{ :bytes => reloaded } } = image |> limits( fail_on: :error ) |> resize( width: 125 ) |> png() |> to_bytes()
+      assert reloaded == thumbnail
+    end
+  end
+
+  test "Resize with kernel and linear light" do
+    result = from_file( "test/input.png" )
+    |> resize( width: 64, kernel: :nearest )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 64, 48 ] }
+
+    result = from_file( "test/vertical.jpg" )
+    |> resize( width: 125, kernel: :lanczos3, linear: true )
+    |> jpg()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 125, 150 ] }
+  end
+
+  test "Resize constraints" do
+    resize_sizes = fn opts ->
+      from_file( "test/input.png" )
+      |> resize( opts )
+      |> png()
+      |> to_bytes()
+      |> from_bytes()
+      |> get_image_sizes()
+    end
+
+    assert resize_sizes.( longest_edge: 320 ) == { :ok, [ 320, 243 ] }
+    assert resize_sizes.( width: 1280, without_enlargement: true ) == { :ok, [ 640, 486 ] }
+    assert resize_sizes.( max_width: 320 ) == { :ok, [ 320, 243 ] }
+    assert resize_sizes.( scale: 2 ) == { :ok, [ 1280, 972 ] }
+
+    # a side scaled below a pixel by a maximum is kept at one, without the other side going past its maximum
+    { :ok, tall } = from_file( "test/input.png" ) |> resize( width: 10, height: 2000, resize_type: :fill ) |> png() |> to_bytes()
+    for opts <- [ [ width: 10, max_height: 10 ], [ max_height: 10 ] ] do
+      assert tall |> resize( opts ) |> png() |> to_bytes() |> from_bytes() |> get_image_sizes() == { :ok, [ 1, 10 ] }
+    end
+
+    assert { :error, _ } = from_file( "test/input.png" )
+    |> resize( scale: 0.5, width: 100 )
+    |> to_bytes()
+  end
+
+  test "Crop before and after resize" do
+    result = from_file( "test/input.png" )
+    |> crop( left: 40, top: 43, width: 400, height: 300 )
+    |> resize( width: 200 )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 200, 150 ] }
+
+    result = from_file( "test/input.png" )
+    |> resize( width: 320 )
+    |> crop( left: 0, top: 0, width: 100, height: 50, stage: :after )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 100, 50 ] }
+
+    assert { :error, _ } = from_file( "test/input.png" )
+    |> crop( left: 600, top: 0, width: 100, height: 100 )
+    |> png()
+    |> to_bytes()
+  end
+
+  test "EXIF autorotate" do
+    file = File.open!( "test/vertical.jpg", [ :read ] )
+    << 0xFF, 0xD8, rest::binary >> = IO.binread( file, :eof )
+
+    # orientation 6, rotate 90 degrees clockwise
+    exif = << "Exif", 0, 0, "MM", 0, 42, 8::32, 1::16, 0x0112::16, 3::16, 1::32, 6::16, 0::16, 0::32 >>
+    bytes = << 0xFF, 0xD8, 0xFF, 0xE1, ( byte_size( exif ) + 2 )::16, exif::binary, rest::binary >>
+
+    assert get_image_sizes( from_bytes( bytes ) ) == { :ok, [ 300, 250 ] }
+    assert get_image_sizes( from_bytes( bytes ) |> autorotate( false ) ) == { :ok, [ 250, 300 ] }
+
+    output = from_bytes( bytes )
+    |> resize( width: 150 )
+    |> jpg( strip: false )
+    |> to_bytes()
+    |> from_bytes()
+
+    # the orientation tag is gone, so both report the same sizes
+    assert get_image_sizes( output ) == { :ok, [ 150, 125 ] }
+    assert get_image_sizes( output |> autorotate( false ) ) == { :ok, [ 150, 125 ] }
+  end
+
+  test "Rotate and flip" do
+    rotate_sizes = fn angle ->
+      from_file( "test/input.png" )
+      |> rotate( angle )
+      |> png()
+      |> to_bytes()
+      |> from_bytes()
+      |> get_image_sizes()
+    end
+
+    assert rotate_sizes.( 90 ) == { :ok, [ 486, 640 ] }
+    assert rotate_sizes.( -180 ) == { :ok, [ 640, 486 ] }
+
+    { :ok, [ width, height ] } = rotate_sizes.( 45 )
+    assert width > 640 and height > 486
+
+    result = from_file( "test/input.png" )
+    |> flip( :horizontal )
+    |> flip( :vertical )
+    |> resize( width: 320 )
+    |> png()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 320, 243 ] }
+  end
+
+  test "Embed on a fixed canvas" do
+    result = from_file( "test/input2.png" )
+    |> resize( width: 1200, height: 630, resize_type: :inside )
+    |> embed( width: 1200, height: 630, gravity: :west, background: [ 255, 255, 255 ] )
+    |> jpg()
+    |> to_bytes()
+    |> from_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 1200, 630 ] }
+  end
+
+  test "Trim borders" do
+    { :ok, bordered } = from_file( "test/input.png" )
+    |> resize( width: 200, height: 200, resize_type: :inside )
+    |> embed( width: 300, height: 300, background: [ 255, 0, 255 ] )
+    |> png()
+    |> to_bytes()
+
+    assert get_trim_box( bordered ) == { :ok, [ 50, 74, 200, 152 ] }
+
+    result = bordered
+    |> trim()
+    |> png()
+    |> to_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 200, 152 ] }
+  end
+
+  test "Affine transform" do
+    result = from_file( "test/input.png" )
+    |> affine( [ 0.5, 0, 0, 0.5 ] )
+    |> png()
+    |> to_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 320, 243 ] }
+
+    assert { :error, _ } = from_file( "test/input.png" )
+    |> affine( [ 1, 1, 1, 1 ] )
+    |> to_bytes()
+  end
+
+  test "Perspective correction" do
+    result = from_file( "test/input.png" )
+    |> perspective( [ { 40, 30 }, { 600, 10 }, { 630, 470 }, { 20, 440 } ], width: 300, height: 200, interpolate: :bicubic )
+    |> jpg()
+    |> to_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 300, 200 ] }
+
+    result = from_file( "test/input.png" )
+    |> perspective( [ { 0, 0 }, { 100, 0 }, { 100, 50 }, { 0, 50 } ] )
+    |> png()
+    |> to_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 100, 50 ] }
+  end
+
+  test "Operations list" do
+    { :ok, overlay } = from_file( "test/input2.png" )
+    |> resize( width: 100 )
+    |> png()
+    |> to_bytes()
+
+    result = from_file( "test/input.png" )
+    |> operations( [
+      resize: [ width: 320 ],
+      rotate: 90,
+      blur: 1.5,
+      composite: [ image: overlay, gravity: :south_east, left: -10, top: -10, blend: :multiply ],
+      crop: [ left: 10, top: 10, width: 200, height: 300 ],
+      flip: :horizontal,
+    ] )
+    |> jpg()
+    |> to_bytes()
+    |> get_image_sizes()
+
+    assert result == { :ok, [ 200, 300 ] }
+
+    assert { :error, "operation 1 (crop) failed: " <> _ } = from_file( "test/input.png" )
+    |> operations( [ resize: [ width: 100 ], crop: [ left: 0, top: 0, width: 200, height: 200 ] ] )
+    |> to_bytes()
+
+    assert { :error, _ } = from_file( "test/input.png" )
+    |> operations( [ sharpen: 2 ] )
+
+    { :ok, large_overlay } = from_file( "test/input.png" ) |> resize( width: 800 ) |> png() |> to_bytes()
+    assert { :error, "operation 0 (composite) failed: failed to open overlay: max_width exceeded: 800 > 640" } = from_file( "test/input.png" )
+    |> limits( max_width: 640 )
+    |> operations( [ composite: [ image: large_overlay ] ] )
+    |> png()
+    |> to_bytes()
+
+    { :ok, resized_overlay } = from_file( "test/input2.png" ) |> resize( width: 100 )
+    assert { :error, "composite overlay can't have processing options, got: [:resize]" } = from_file( "test/input.png" )
+    |> operations( [ composite: [ image: resized_overlay ] ] )
+  end
+
+  test "Loaded image is reused" do
+    { :ok, image } = from_file( "test/vertical.jpg" ) |> load()
+
+    assert get_image_sizes( image ) == { :ok, [ 250, 300 ] }
+    assert get_image_format( image ) == { :ok, :jpg }
+
+    for { width, encode } <- [ { 100, &jpg/1 }, { 200, &webp/1 }, { 50, &png/1 } ] do
+      result = image
+      |> resize( width: width )
+      |> encode.()
+      |> to_bytes()
+      |> get_image_sizes()
+
+      assert result == { :ok, [ width, round( width * 300 / 250 ) ] }
+    end
+
+    file = File.open!( "test/input.png", [ :read ] )
+    bytes = IO.binread( file, :eof )
+
+    { :ok, image } = from_bytes( bytes ) |> load()
+    assert { :ok, %Elxvips.ImageBytes{} } = image |> rotate( 90 ) |> png() |> to_bytes()
+    assert get_image_sizes( image ) == { :ok, [ 640, 486 ] }
+  end
+
+  test "Variants from one decode" do
+    { :ok, results } = from_file( "test/input.png" )
+    |> rotate( 180 )
+    |> to_variants( [
+      [ resize: [ width: 320 ], format: :webp, quality: 80 ],
+      [ resize: [ width: 100, height: 100, resize_type: :cover ], format: :png ],
+      [ resize: [ width: 160 ] ],
+      [ resize: [ width: -1 ], format: :jpg ],
+    ] )
+
+    assert [ { :ok, webp }, { :ok, png }, { :ok, jpg }, { :error, _ } ] = results
+    assert get_image_sizes( webp ) == { :ok, [ 320, 243 ] }
+    assert get_image_format( webp ) == { :ok, :webp }
+    assert get_image_sizes( png ) == { :ok, [ 100, 100 ] }
+    assert { :ok, [ 160, _ ] } = get_image_sizes( jpg )
+    assert get_image_format( jpg ) == { :ok, :jpg }
+
+    assert { :error, _ } = from_file( "test/input.png" ) |> to_variants( [ [ format: :gif ] ] )
+  end
+
+  test "Async processing", %{ large: large } do
+    { :ok, job } = from_file( "test/input.png" )
+    |> resize( width: 100 )
+    |> png()
+    |> to_bytes_async()
+
+    assert_receive { ^job, { :ok, bytes } }, 5_000
+    assert from_bytes( bytes ) |> get_image_sizes() == { :ok, [ 100, 76 ] }
+
+    { :ok, job } = from_file( "test/missing.png" ) |> to_bytes_async()
+    assert { :error, "failed to open image: " <> _ } = await( job, 5_000 )
+
+    { :ok, job } = from_file( large )
+    |> resize( width: 4000 )
+    |> jpg()
+    |> to_bytes_async()
+
+    assert :ok = cancel( job )
+    refute_receive { ^job, _ }, 1_000
+
+    # cancelling stops the transform before the encoding too, every worker is free again right away
+    jobs = for _ <- 1..System.schedulers_online() do
+      { :ok, job } = from_file( large )
+      |> operations( [ blur: 50, trim: [] ] )
+      |> jpg()
+      |> to_bytes_async()
+
+      job
+    end
+
+    Process.sleep( 200 )
+    Enum.each( jobs, fn job -> assert :ok = cancel( job ) end )
+
+    { :ok, job } = from_file( "test/input.png" )
+    |> resize( width: 100 )
+    |> png()
+    |> to_bytes_async()
+
+    assert { :ok, _ } = await( job, 5_000 )
+  end
+
+  test "Streaming input and output" do
+    chunks = File.read!( "test/input.png" )
+    |> Stream.unfold( fn
+      <<>> -> nil
+      <<chunk::binary-size( 4096 ), rest::binary>> -> { chunk, rest }
+      rest -> { rest, <<>> }
+    end )
+
+    output = from_stream( chunks )
+    |> resize( width: 320 )
+    |> jpg()
+    |> to_stream()
+    |> Enum.into( <<>> )
+
+    assert from_bytes( output ) |> get_image_sizes() == { :ok, [ 320, 243 ] }
+    assert from_bytes( output ) |> get_image_format() == { :ok, :jpg }
+
+    output = from_file( "test/input.png" )
+    |> resize( width: 100 )
+    |> png()
+    |> to_stream()
+    |> Enum.to_list()
+    |> IO.iodata_to_binary()
+
+    assert from_bytes( output ) |> get_image_sizes() == { :ok, [ 100, 76 ] }
+
+    assert [ _ ] = from_file( "test/input.png" ) |> png() |> to_stream() |> Enum.take( 1 )
+    refute_receive { _, { :chunk, _ } }, 500
+
+    assert_raise RuntimeError, ~r/^failed to open image/, fn ->
+      from_stream( [ "not an image" ] ) |> png() |> to_stream() |> Stream.run()
+    end
+
+    assert_raise RuntimeError, "{:limit_exceeded, :max_width, 640, 100}", fn ->
+      from_file( "test/input.png" ) |> limits( max_width: 100 ) |> png() |> to_stream() |> Stream.run()
+    end
+  end
+
+  test "Timeout and progress", %{ large: large } do
+    result = from_file( large )
+    |> resize( width: 4000 )
+    |> jpg()
+    |> timeout( 1 )
+    |> to_bytes()
+    assert result == { :error, :timeout }
+
+    { :ok, job } = from_file( large )
+    |> resize( width: 4000 )
+    |> jpg()
+    |> timeout( 1 )
+    |> to_bytes_async()
+    assert await( job, 5_000 ) == { :error, :timeout }
+
+    # steps computing pixels before the encoding are held to the deadline as well
+    result = from_file( large )
+    |> resize( width: 4000, height: 4000, gravity: :attention )
+    |> jpg()
+    |> timeout( 1 )
+    |> to_bytes()
+    assert result == { :error, :timeout }
+
+    assert from_file( large ) |> timeout( 1 ) |> get_trim_box() == { :error, :timeout }
+    assert from_file( large ) |> timeout( 1 ) |> load() == { :error, :timeout }
+    assert from_file( large ) |> timeout( 1 ) |> to_variants( [ [ resize: [ width: 100 ] ] ] ) == { :error, :timeout }
+    assert process_batch( [ from_file( large ) |> resize( width: 4000 ) |> jpg() |> timeout( 1 ) ] ) == { :ok, [ { :error, :timeout } ] }
+
+    { :ok, _ } = from_file( "test/input.png" )
+    |> resize( width: 100 )
+    |> png()
+    |> timeout( 5_000 )
+    |> progress( tag: :input )
+    |> to_bytes()
+    assert_received { :elxvips_progress, :input, 100 }
+  end
+
+  test "Batch processing" do
+    output = Path.join( System.tmp_dir!(), "elxvips_batch_#{System.unique_integer( [ :positive ] )}.jpg" )
+    on_exit( fn -> File.rm( output ) end )
+
+    result = process_batch( [
+      from_file( "test/input.png" ) |> resize( width: 100 ) |> png(),
+      { from_file( "test/input.png" ) |> resize( width: 50 ) |> jpg(), output },
+      from_file( "test/missing.png" ) |> png(),
+      from_file( "test/input.png" ) |> limits( max_width: 10 ) |> png(),
+    ], width: 2 )
+
+    assert { :ok, [ { :ok, bytes }, { :ok, ^output }, { :error, _ }, { :error, limit } ] } = result
+    assert from_bytes( bytes ) |> get_image_sizes() == { :ok, [ 100, 76 ] }
+    assert from_file( output ) |> get_image_format() == { :ok, :jpg }
+    assert limit == { :limit_exceeded, :max_width, 640, 10 }
+
+    assert process_batch( [] ) == { :ok, [] }
+    assert { :error, _ } = process_batch( [ :not_an_image ] )
+    assert { :error, _ } = process_batch( [ from_file( "test/input.png" ) ], width: 0 )
+
+    images = List.duplicate( from_file( "test/input.png" ) |> resize( width: 300 ) |> png(), 20 )
+    assert process_batch( images, width: 1, timeout: 0 ) == { :error, :timeout }
+    refute_receive { _, { :ok, _ } }, 1_000
+  end
+
+  test "Operation blocking" do
+    # blocking is global to libvips, the loaders are allowed again even when an assertion fails halfway
+    on_exit( fn -> set_block( allow: [ "pngload", "jpegload" ] ) end )
+
+    { :ok, loaders } = get_allowed_loaders()
+    assert "pngload" in loaders
+
+    assert set_block( block: [ "pngload" ] ) == :ok
+    { :ok, loaders } = get_allowed_loaders()
+    refute "pngload" in loaders
+    assert { :error, _ } = from_file( "test/input.png" ) |> png() |> to_bytes()
+
+    assert set_block( allow: [ "pngload" ] ) == :ok
+    { :ok, loaders } = get_allowed_loaders()
+    assert "pngload" in loaders
+    assert { :ok, _ } = from_file( "test/input.png" ) |> png() |> to_bytes()
+
+    assert { :error, _ } = set_block( block: [ "jpegload" ], allow: [ "notanoperation" ] )
+    { :ok, loaders } = get_allowed_loaders()
+    assert "jpegload" in loaders
+  end
+
+  test "Block options config" do
+    on_exit( fn -> Application.delete_env( :elxvips, :block_options ) end )
+
+    Application.put_env( :elxvips, :block_options, block: [ "notanoperation" ] )
+    assert { :error, "operation notanoperation not found" } = Elxvips.Application.apply_block_config()
+
+    Application.put_env( :elxvips, :block_options, block_untrusted: "yes" )
+    assert { :error, "block_untrusted must be a boolean" } = Elxvips.Application.apply_block_config()
+
+    Application.put_env( :elxvips, :block_options, "block everything" )
+    assert { :error, "block_options must be a keyword list, " <> _ } = Elxvips.Application.apply_block_config()
+
+    Application.put_env( :elxvips, :block_options, allow: [ "pngload" ] )
+    assert :ok = Elxvips.Application.apply_block_config()
+  end
+
+  test "Load limits" do
+    result = from_file( "test/input.png" )
+    |> limits( max_width: 100 )
+    |> png()
+    |> to_bytes()
+    assert result == { :error, { :limit_exceeded, :max_width, 640, 100 } }
+
+    { :ok, bytes } = File.read( "test/input.png" )
+    result = from_bytes( bytes )
+    |> limits( max_bytes: 10 )
+    |> png()
+    |> to_bytes()
+    assert result == { :error, { :limit_exceeded, :max_bytes, byte_size( bytes ), 10 } }
+
+    { :ok, job } = from_file( "test/input.png" )
+    |> limits( max_pixels: 1000 )
+    |> png()
+    |> to_bytes_async()
+    assert await( job, 5_000 ) == { :error, { :limit_exceeded, :max_pixels, 640 * 486, 1000 } }
+
+    { :ok, _ } = from_file( "test/input.png" )
+    |> limits( max_width: 640, max_height: 486, max_pages: 1, fail_on: :warning )
+    |> resize( width: 100 )
+    |> png()
+    |> to_bytes()
+
+    assert limits( from_file( "test/input.png" ), fail_on: :never ) |> elem( 0 ) == :error
+  end
+
   test "8K Image" do
     result = from_file( "test/8k.jpg" )
     |> resize( height: 720 )